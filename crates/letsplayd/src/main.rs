@@ -8,4 +8,13 @@ fn main() {
 		.finish();
 
 	tracing::subscriber::set_global_default(subscriber).unwrap();
+
+	// Each emulator runs in its own copy of the daemon, started with
+	// `Command::new(std::env::current_exe()?).arg("runner")`.
+	if std::env::args().nth(1).as_deref() == Some("runner") {
+		if let Err(err) = retro_frontend::remote::run_runner() {
+			tracing::error!("Runner failed: {err}");
+			std::process::exit(1);
+		}
+	}
 }
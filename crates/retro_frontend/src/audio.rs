@@ -0,0 +1,346 @@
+//! Audio buffering and resampling.
+//!
+//! Cores produce audio at whatever rate they like (and at slightly different rates than they claim,
+//! since their video and audio clocks are rarely in perfect agreement). When configured, the frontend
+//! resamples core audio to a fixed output rate and hands it out in fixed-size chunks, which is what
+//! encoders and audio devices want.
+
+use crate::frontend::FrontendInterface;
+use crate::libretro_sys_new::SystemTiming;
+use crate::result::{Error, Result};
+
+use tracing::warn;
+
+/// Configuration for resampling core audio.
+#[derive(Clone, Debug)]
+pub struct AudioConfig {
+	/// Sample rate audio is resampled to.
+	pub output_rate: u32,
+
+	/// Number of stereo frames in each chunk given to [FrontendInterface::audio_sample].
+	pub chunk_frames: usize,
+
+	/// The most the resampling ratio will be adjusted by to keep the amount of audio produced in
+	/// line with the video frame rate. 0.005 (half a percent) is inaudible.
+	pub max_rate_delta: f64,
+}
+
+impl Default for AudioConfig {
+	fn default() -> Self {
+		Self {
+			output_rate: 48000,
+			chunk_frames: 1024,
+			max_rate_delta: 0.005,
+		}
+	}
+}
+
+/// A fixed-capacity ring buffer of stereo frames. When full, the oldest frames are overwritten.
+struct RingBuffer {
+	frames: Vec<[i16; 2]>,
+	head: usize,
+	len: usize,
+}
+
+impl RingBuffer {
+	fn new(capacity: usize) -> Self {
+		Self {
+			frames: vec![[0; 2]; capacity.max(1)],
+			head: 0,
+			len: 0,
+		}
+	}
+
+	fn len(&self) -> usize {
+		self.len
+	}
+
+	fn clear(&mut self) {
+		self.head = 0;
+		self.len = 0;
+	}
+
+	/// Pushes a frame, returning false if the oldest frame had to be overwritten to make room.
+	fn push(&mut self, frame: [i16; 2]) -> bool {
+		let capacity = self.frames.len();
+		let tail = (self.head + self.len) % capacity;
+		self.frames[tail] = frame;
+
+		if self.len == capacity {
+			self.head = (self.head + 1) % capacity;
+			return false;
+		}
+
+		self.len += 1;
+		true
+	}
+
+	/// Pops `count` frames as interleaved samples into `out`.
+	fn pop_interleaved(&mut self, count: usize, out: &mut Vec<i16>) {
+		let count = count.min(self.len);
+		out.clear();
+
+		for i in 0..count {
+			let frame = self.frames[(self.head + i) % self.frames.len()];
+			out.extend_from_slice(&frame);
+		}
+
+		self.head = (self.head + count) % self.frames.len();
+		self.len -= count;
+	}
+}
+
+/// Streaming cubic (Catmull-Rom) resampler for stereo audio.
+struct Resampler {
+	/// The last four input frames.
+	history: [[f32; 2]; 4],
+
+	/// Position between `history[1]` and `history[2]` of the next output frame.
+	position: f64,
+}
+
+impl Resampler {
+	fn new() -> Self {
+		Self {
+			history: [[0.0; 2]; 4],
+			position: 0.0,
+		}
+	}
+
+	/// Pushes one input frame, calling `output` for every output frame this produces.
+	/// `step` is how many input frames each output frame advances by.
+	fn push(&mut self, frame: [i16; 2], step: f64, mut output: impl FnMut([i16; 2])) {
+		self.history.rotate_left(1);
+		self.history[3] = [frame[0] as f32, frame[1] as f32];
+
+		while self.position < 1.0 {
+			output(self.interpolate(self.position as f32));
+			self.position += step;
+		}
+
+		self.position -= 1.0;
+	}
+
+	fn interpolate(&self, t: f32) -> [i16; 2] {
+		let mut frame = [0; 2];
+
+		for (channel, sample) in frame.iter_mut().enumerate() {
+			let [x0, x1, x2, x3] = self.history.map(|frame| frame[channel]);
+
+			let c1 = 0.5 * (x2 - x0);
+			let c2 = x0 - 2.5 * x1 + 2.0 * x2 - 0.5 * x3;
+			let c3 = 0.5 * (x3 - x0) + 1.5 * (x1 - x2);
+
+			let value = ((c3 * t + c2) * t + c1) * t + x1;
+			*sample = value.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+		}
+
+		frame
+	}
+}
+
+/// Takes audio from the core and gives it to the [FrontendInterface]. Without an [AudioConfig],
+/// audio is passed through as the core gave it.
+pub(crate) struct AudioPipeline {
+	config: Option<AudioConfig>,
+
+	/// Rate the core produces audio at, and its frame rate.
+	input_rate: f64,
+	fps: f64,
+
+	/// Frames given one at a time by the core, which are batched up before being processed.
+	pending: Vec<i16>,
+
+	resampler: Resampler,
+	buffer: RingBuffer,
+	chunk: Vec<i16>,
+
+	/// How many more output frames have been produced than the frame rate says there should have been.
+	drift_frames: f64,
+
+	/// The current adjustment to the resampling ratio.
+	rate_adjust: f64,
+
+	/// If set, audio from the core is thrown away.
+	muted: bool,
+}
+
+impl AudioPipeline {
+	pub fn new() -> Self {
+		Self {
+			config: None,
+			input_rate: 0.0,
+			fps: 0.0,
+			pending: Vec::new(),
+			resampler: Resampler::new(),
+			buffer: RingBuffer::new(0),
+			chunk: Vec::new(),
+			drift_frames: 0.0,
+			rate_adjust: 0.0,
+			muted: false,
+		}
+	}
+
+	/// Sets (or with [None], removes) the resampling configuration.
+	pub fn set_config(&mut self, config: Option<AudioConfig>) -> Result<()> {
+		if let Some(config) = config.as_ref() {
+			if config.output_rate == 0 {
+				return Err(Error::InvalidAudioConfig("output rate is 0".into()));
+			}
+
+			if config.chunk_frames == 0 {
+				return Err(Error::InvalidAudioConfig("chunk size is 0".into()));
+			}
+
+			if !(config.max_rate_delta >= 0.0 && config.max_rate_delta < 1.0) {
+				return Err(Error::InvalidAudioConfig(format!(
+					"maximum rate delta {} is not between 0 and 1",
+					config.max_rate_delta
+				)));
+			}
+
+			// Leave plenty of room for cores which produce audio in bursts.
+			let capacity = (config.chunk_frames * 4).max(config.output_rate as usize / 2);
+			self.buffer = RingBuffer::new(capacity);
+		}
+
+		self.config = config;
+		self.reset();
+		Ok(())
+	}
+
+	/// Sets the rate the core produces audio and video at. With an [AudioConfig], audio is
+	/// dropped until the core gives a valid sample rate.
+	pub fn set_timing(&mut self, timing: &SystemTiming) {
+		if self.config.is_some() && !Self::valid_rate(timing.sample_rate) {
+			warn!(
+				"Core gave a sample rate of {}, its audio will be dropped",
+				timing.sample_rate
+			);
+		}
+
+		self.input_rate = timing.sample_rate;
+		self.fps = timing.fps;
+		self.reset();
+	}
+
+	/// Mutes or unmutes audio. While muted, audio from the core is thrown away.
+	pub fn set_muted(&mut self, muted: bool) {
+		if muted != self.muted {
+			self.reset();
+		}
+
+		self.muted = muted;
+	}
+
+	pub fn is_muted(&self) -> bool {
+		self.muted
+	}
+
+	/// Throws away any buffered audio.
+	pub fn reset(&mut self) {
+		self.pending.clear();
+		self.resampler = Resampler::new();
+		self.buffer.clear();
+		self.drift_frames = 0.0;
+		self.rate_adjust = 0.0;
+	}
+
+	/// Queues a single stereo frame from the core.
+	pub fn push_frame(&mut self, left: i16, right: i16) {
+		if self.muted {
+			return;
+		}
+
+		self.pending.extend_from_slice(&[left, right]);
+	}
+
+	/// Processes interleaved stereo samples from the core.
+	pub fn push_samples(&mut self, samples: &[i16], interface: &mut dyn FrontendInterface) {
+		if self.muted {
+			return;
+		}
+
+		self.flush_pending(interface);
+		self.process(samples, interface);
+	}
+
+	/// Called once the core has finished running a frame. Updates rate control and
+	/// hands out any audio which is ready.
+	pub fn end_frame(&mut self, interface: &mut dyn FrontendInterface) {
+		if self.muted {
+			return;
+		}
+
+		self.flush_pending(interface);
+
+		let Some(config) = self.config.as_ref() else {
+			return;
+		};
+
+		if self.fps <= 0.0 {
+			return;
+		}
+
+		// Nudge the resampling ratio so that, on average, one video frame's worth of audio
+		// is produced every frame. Being a whole frame off uses the maximum adjustment.
+		let frame_len = config.output_rate as f64 / self.fps;
+		self.drift_frames -= frame_len;
+		self.rate_adjust = (self.drift_frames / frame_len * config.max_rate_delta)
+			.clamp(-config.max_rate_delta, config.max_rate_delta);
+	}
+
+	fn valid_rate(rate: f64) -> bool {
+		rate.is_finite() && rate > 0.0
+	}
+
+	fn flush_pending(&mut self, interface: &mut dyn FrontendInterface) {
+		if self.pending.is_empty() {
+			return;
+		}
+
+		let pending = std::mem::take(&mut self.pending);
+		self.process(&pending, interface);
+
+		// Keep the allocation around for next time.
+		self.pending = pending;
+		self.pending.clear();
+	}
+
+	fn process(&mut self, samples: &[i16], interface: &mut dyn FrontendInterface) {
+		let Some(config) = self.config.as_ref() else {
+			interface.audio_sample(samples, samples.len() / 2);
+			return;
+		};
+
+		// Passing it through would break the promise of chunks at the configured rate.
+		if !Self::valid_rate(self.input_rate) {
+			return;
+		}
+
+		let step = self.input_rate / config.output_rate as f64 * (1.0 + self.rate_adjust);
+		let chunk_frames = config.chunk_frames;
+
+		let mut produced = 0usize;
+		let mut overflowed = false;
+
+		for frame in samples.chunks_exact(2) {
+			let buffer = &mut self.buffer;
+			self.resampler.push([frame[0], frame[1]], step, |frame| {
+				produced += 1;
+				overflowed |= !buffer.push(frame);
+			});
+		}
+
+		if overflowed {
+			warn!("Audio buffer overflowed, dropping audio");
+		}
+
+		self.drift_frames += produced as f64;
+
+		while self.buffer.len() >= chunk_frames {
+			self.buffer.pop_interleaved(chunk_frames, &mut self.chunk);
+			interface.audio_sample(&self.chunk, chunk_frames);
+		}
+	}
+}
@@ -0,0 +1,8 @@
+//! A runner for [retro_frontend::remote::RemoteFrontend]s.
+
+fn main() {
+	if let Err(err) = retro_frontend::remote::run_runner() {
+		eprintln!("Runner failed: {err}");
+		std::process::exit(1);
+	}
+}
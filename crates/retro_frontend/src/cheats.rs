@@ -0,0 +1,91 @@
+//! Cheat code support.
+
+use crate::result::{Error, Result};
+use std::collections::HashMap;
+
+/// A single cheat.
+#[derive(Clone, Debug)]
+pub struct Cheat {
+	/// Human readable description of the cheat.
+	pub description: String,
+
+	/// The cheat code, in whatever format the core expects.
+	/// Multiple codes can be combined with `+`.
+	pub code: String,
+
+	/// If this cheat should be applied.
+	pub enabled: bool,
+}
+
+impl Cheat {
+	/// Creates a new (disabled) cheat.
+	pub fn new(description: &str, code: &str) -> Self {
+		Self {
+			description: description.to_string(),
+			code: code.to_string(),
+			enabled: false,
+		}
+	}
+}
+
+/// Parses a RetroArch `.cht` cheat file.
+///
+/// Only the fields relevant to core-side cheats (`cheatN_desc`, `cheatN_code` and `cheatN_enable`)
+/// are read; everything else is ignored.
+pub fn parse_cht(data: &str) -> Result<Vec<Cheat>> {
+	let mut values = HashMap::new();
+
+	for line in data.lines() {
+		let line = line.trim();
+
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		let (key, value) = match line.split_once('=') {
+			Some(pair) => pair,
+			None => return Err(Error::InvalidCheatFile(format!("malformed line \"{line}\""))),
+		};
+
+		let value = value.trim();
+		let value = value
+			.strip_prefix('"')
+			.and_then(|v| v.strip_suffix('"'))
+			.unwrap_or(value);
+
+		values.insert(key.trim().to_string(), value.to_string());
+	}
+
+	let count = match values.get("cheats") {
+		Some(count) => count
+			.parse::<usize>()
+			.map_err(|_| Error::InvalidCheatFile(format!("invalid cheat count \"{count}\"")))?,
+		None => return Err(Error::InvalidCheatFile("missing cheat count".into())),
+	};
+
+	let mut cheats = Vec::new();
+
+	for i in 0..count {
+		let code = match values.get(&format!("cheat{i}_code")) {
+			Some(code) => code,
+			None => return Err(Error::InvalidCheatFile(format!("cheat {i} has no code"))),
+		};
+
+		let description = values
+			.get(&format!("cheat{i}_desc"))
+			.cloned()
+			.unwrap_or_else(|| format!("Cheat {i}"));
+
+		let enabled = values
+			.get(&format!("cheat{i}_enable"))
+			.is_some_and(|enable| enable == "true");
+
+		cheats.push(Cheat {
+			description,
+			code: code.clone(),
+			enabled,
+		});
+	}
+
+	Ok(cheats)
+}
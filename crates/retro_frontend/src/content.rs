@@ -0,0 +1,449 @@
+//! Content (game) loading, including content stored inside of archives.
+
+use crate::libretro_subsystem::SubsystemRom;
+use crate::libretro_sys_new::*;
+use crate::result::{Error, Result};
+use crate::util;
+use std::collections::HashMap;
+use std::ffi::{self, CString};
+use std::fs;
+use std::io::Read;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use tracing::info;
+
+/// Archive formats we can extract content from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ArchiveKind {
+	Zip,
+	SevenZip,
+}
+
+impl ArchiveKind {
+	fn from_path(path: &Path) -> Option<Self> {
+		match lowercase_extension(path).as_str() {
+			"zip" => Some(Self::Zip),
+			"7z" => Some(Self::SevenZip),
+			_ => None,
+		}
+	}
+
+	fn extension(self) -> &'static str {
+		match self {
+			Self::Zip => "zip",
+			Self::SevenZip => "7z",
+		}
+	}
+}
+
+fn lowercase_extension(path: &Path) -> String {
+	path.extension()
+		.map(|ext| ext.to_string_lossy().to_lowercase())
+		.unwrap_or_default()
+}
+
+fn c_path(path: &Path) -> Result<CString> {
+	CString::new(path.as_os_str().as_bytes()).map_err(|_| Error::InvalidPath(path.to_path_buf()))
+}
+
+fn c_string(string: &str) -> CString {
+	// Names inside archives containing NUL are just not going to happen.
+	CString::new(string).unwrap_or_default()
+}
+
+/// Overrides a core gave for how content with a given extension should be loaded.
+/// See [RETRO_ENVIRONMENT_SET_CONTENT_INFO_OVERRIDE].
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ContentInfoOverride {
+	pub need_fullpath: bool,
+	pub persistent_data: bool,
+}
+
+/// How a core wants content to be given to it.
+pub(crate) struct ContentRequirements {
+	/// Lowercase extensions the core can load. If empty, the core didn't say.
+	pub valid_extensions: Vec<String>,
+	pub need_fullpath: bool,
+	pub block_extract: bool,
+
+	/// Per-extension overrides of the above.
+	pub overrides: HashMap<String, ContentInfoOverride>,
+}
+
+impl ContentRequirements {
+	/// Creates content requirements from a core's system info.
+	///
+	/// # Safety
+	/// The pointers in the given [SystemInfo] must be valid, as the libretro API requires.
+	pub unsafe fn from_system_info(
+		system_info: &SystemInfo,
+		overrides: &HashMap<String, ContentInfoOverride>,
+	) -> Self {
+		Self {
+			valid_extensions: util::extensions_from_c(system_info.valid_extensions),
+			need_fullpath: system_info.need_fullpath,
+			block_extract: system_info.block_extract,
+			overrides: overrides.clone(),
+		}
+	}
+
+	/// Creates content requirements for a piece of subsystem content.
+	pub fn from_subsystem_rom(
+		rom: &SubsystemRom,
+		overrides: &HashMap<String, ContentInfoOverride>,
+	) -> Self {
+		Self {
+			valid_extensions: rom.valid_extensions.clone(),
+			need_fullpath: rom.need_fullpath,
+			block_extract: rom.block_extract,
+			overrides: overrides.clone(),
+		}
+	}
+
+	/// Returns true if the content at the given path can be given to the core (possibly after extracting it).
+	pub fn accepts(&self, path: &Path) -> bool {
+		split_archive_path(path).is_some() || self.supports_extension(&lowercase_extension(path))
+	}
+
+	/// Whether the core wants a path instead of data for content with the given extension.
+	fn need_fullpath(&self, ext: &str) -> bool {
+		self.overrides
+			.get(ext)
+			.map_or(self.need_fullpath, |over| over.need_fullpath)
+	}
+
+	/// Whether the core wants content data with the given extension kept around until the game is unloaded.
+	fn persistent_data(&self, ext: &str) -> bool {
+		self.overrides
+			.get(ext)
+			.is_some_and(|over| over.persistent_data)
+	}
+
+	fn supports_extension(&self, ext: &str) -> bool {
+		self.valid_extensions.is_empty() || self.valid_extensions.iter().any(|valid| valid == ext)
+	}
+}
+
+/// Splits a content path into an archive path and (optionally) the file inside of it to load.
+/// Files inside archives can be selected with the `archive.zip#file.ext` syntax.
+fn split_archive_path(path: &Path) -> Option<(PathBuf, ArchiveKind, Option<String>)> {
+	if let Some(kind) = ArchiveKind::from_path(path) {
+		return Some((path.to_path_buf(), kind, None));
+	}
+
+	let (archive, file) = path.to_str()?.rsplit_once('#')?;
+	let kind = ArchiveKind::from_path(Path::new(archive))?;
+	Some((archive.into(), kind, Some(file.to_string())))
+}
+
+/// Lists the names of all the files in an archive.
+fn list_archive(archive_path: &Path, kind: ArchiveKind) -> Result<Vec<String>> {
+	match kind {
+		ArchiveKind::Zip => {
+			let mut archive = zip::ZipArchive::new(fs::File::open(archive_path)?)
+				.map_err(|err| Error::ArchiveError(err.to_string()))?;
+
+			let mut names = Vec::with_capacity(archive.len());
+			for i in 0..archive.len() {
+				let file = archive
+					.by_index(i)
+					.map_err(|err| Error::ArchiveError(err.to_string()))?;
+
+				if !file.is_dir() {
+					names.push(file.name().to_string());
+				}
+			}
+
+			Ok(names)
+		}
+
+		ArchiveKind::SevenZip => {
+			let reader =
+				sevenz_rust::SevenZReader::open(archive_path, sevenz_rust::Password::empty())
+					.map_err(|err| Error::ArchiveError(err.to_string()))?;
+
+			Ok(reader
+				.archive()
+				.files
+				.iter()
+				.filter(|entry| !entry.is_directory() && entry.has_stream)
+				.map(|entry| entry.name().to_string())
+				.collect())
+		}
+	}
+}
+
+/// Reads a file inside of an archive into memory.
+fn read_archive_file(archive_path: &Path, kind: ArchiveKind, name: &str) -> Result<Vec<u8>> {
+	match kind {
+		ArchiveKind::Zip => {
+			let mut archive = zip::ZipArchive::new(fs::File::open(archive_path)?)
+				.map_err(|err| Error::ArchiveError(err.to_string()))?;
+
+			let mut file = archive
+				.by_name(name)
+				.map_err(|err| Error::ArchiveError(err.to_string()))?;
+
+			// The size in the archive can't be trusted, so let the buffer grow as it's read.
+			let mut data = Vec::new();
+			file.read_to_end(&mut data)?;
+			Ok(data)
+		}
+
+		ArchiveKind::SevenZip => {
+			let mut reader =
+				sevenz_rust::SevenZReader::open(archive_path, sevenz_rust::Password::empty())
+					.map_err(|err| Error::ArchiveError(err.to_string()))?;
+
+			let mut data = None;
+			reader
+				.for_each_entries(|entry, entry_reader| {
+					if entry.name() != name {
+						return Ok(true);
+					}
+
+					let mut buffer = Vec::new();
+					entry_reader.read_to_end(&mut buffer)?;
+					data = Some(buffer);
+					Ok(false)
+				})
+				.map_err(|err| Error::ArchiveError(err.to_string()))?;
+
+			data.ok_or_else(|| Error::ArchiveError(format!("{name} is not in the archive")))
+		}
+	}
+}
+
+/// An entry in a `.m3u` playlist of disk images.
+pub(crate) struct PlaylistEntry {
+	pub path: PathBuf,
+	pub label: Option<String>,
+}
+
+/// Returns true if the given path is a `.m3u` playlist.
+pub(crate) fn is_playlist(path: &Path) -> bool {
+	lowercase_extension(path) == "m3u"
+}
+
+/// Parses a `.m3u` playlist of disk images. Relative paths are relative to the playlist,
+/// and entries can be given a label with the `path|label` syntax.
+pub(crate) fn parse_m3u(path: &Path) -> Result<Vec<PlaylistEntry>> {
+	let playlist = fs::read_to_string(path)?;
+	let base = path.parent().unwrap_or(Path::new(""));
+
+	let entries: Vec<_> = playlist
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
+		.map(|line| {
+			let (entry_path, label) = match line.split_once('|') {
+				Some((entry_path, label)) => (entry_path, Some(label.trim().to_string())),
+				None => (line, None),
+			};
+
+			PlaylistEntry {
+				path: base.join(entry_path.trim()),
+				label: label.filter(|label| !label.is_empty()),
+			}
+		})
+		.collect();
+
+	if entries.is_empty() {
+		return Err(Error::EmptyPlaylist(path.to_path_buf()));
+	}
+
+	Ok(entries)
+}
+
+/// Content which has been loaded for a core. This owns everything that the [GameInfo] and
+/// [GameInfoExt] given to the core point into, so it must be kept alive as long as the game is.
+pub(crate) struct LoadedContent {
+	/// The content file (or archive) on disk.
+	source_path: PathBuf,
+
+	/// The path we give to the core in [GameInfo].
+	path: CString,
+
+	full_path: Option<CString>,
+	archive_path: Option<CString>,
+	archive_file: Option<CString>,
+	dir: CString,
+	name: CString,
+	ext: CString,
+
+	data: Option<Vec<u8>>,
+	file_in_archive: bool,
+	persistent_data: bool,
+
+	/// If content had to be extracted, the temporary directory it was extracted to.
+	/// This is removed once the content is dropped.
+	_temp_dir: Option<tempfile::TempDir>,
+}
+
+impl LoadedContent {
+	/// Loads content from the given path, in the way the core wants it.
+	pub fn load(path: &Path, requirements: &ContentRequirements) -> Result<Self> {
+		if let Some((archive_path, kind, file)) = split_archive_path(path) {
+			// Cores which load archives themselves (or don't want us touching them) get the archive as-is.
+			if !requirements.block_extract && !requirements.supports_extension(kind.extension()) {
+				return Self::load_from_archive(&archive_path, kind, file, requirements);
+			}
+
+			return Self::load_file(&archive_path, requirements);
+		}
+
+		Self::load_file(path, requirements)
+	}
+
+	fn load_file(path: &Path, requirements: &ContentRequirements) -> Result<Self> {
+		let ext = lowercase_extension(path);
+
+		let data = if requirements.need_fullpath(&ext) {
+			None
+		} else {
+			Some(fs::read(path)?)
+		};
+
+		let c_full_path = c_path(path)?;
+
+		Ok(Self {
+			source_path: path.to_path_buf(),
+			path: c_full_path.clone(),
+			full_path: Some(c_full_path),
+			archive_path: None,
+			archive_file: None,
+			dir: c_path(path.parent().unwrap_or(Path::new("")))?,
+			name: c_string(&path.file_stem().unwrap_or_default().to_string_lossy()),
+			ext: c_string(&ext),
+			data,
+			file_in_archive: false,
+			persistent_data: requirements.persistent_data(&ext),
+			_temp_dir: None,
+		})
+	}
+
+	fn load_from_archive(
+		archive_path: &Path,
+		kind: ArchiveKind,
+		file: Option<String>,
+		requirements: &ContentRequirements,
+	) -> Result<Self> {
+		let names = list_archive(archive_path, kind)?;
+
+		// Either use the file the user asked for, or the first file the core can load.
+		let file = match file {
+			Some(file) if names.contains(&file) => file,
+			Some(file) => {
+				return Err(Error::ArchiveError(format!("{file} is not in the archive")));
+			}
+			None => names
+				.into_iter()
+				.find(|name| requirements.supports_extension(&lowercase_extension(Path::new(name))))
+				.ok_or_else(|| Error::NoContentInArchive(archive_path.to_path_buf()))?,
+		};
+
+		let file_path = Path::new(&file);
+		let ext = lowercase_extension(file_path);
+		info!("Loading {file} from archive {}", archive_path.display());
+
+		let contents = read_archive_file(archive_path, kind, &file)?;
+
+		// Cores which need a path get the content extracted to a temporary directory.
+		let (path, full_path, data, temp_dir) = if requirements.need_fullpath(&ext) {
+			let temp_dir = tempfile::Builder::new().prefix("letsplay-").tempdir()?;
+			let extracted_path = temp_dir
+				.path()
+				.join(file_path.file_name().unwrap_or_default());
+
+			fs::write(&extracted_path, contents)?;
+
+			let c_extracted_path = c_path(&extracted_path)?;
+			(
+				c_extracted_path.clone(),
+				Some(c_extracted_path),
+				None,
+				Some(temp_dir),
+			)
+		} else {
+			let mut archive_file_path = archive_path.as_os_str().to_os_string();
+			archive_file_path.push("#");
+			archive_file_path.push(&file);
+
+			(
+				c_path(Path::new(&archive_file_path))?,
+				None,
+				Some(contents),
+				None,
+			)
+		};
+
+		Ok(Self {
+			source_path: archive_path.to_path_buf(),
+			path,
+			full_path,
+			archive_path: Some(c_path(archive_path)?),
+			archive_file: Some(c_string(&file)),
+			dir: c_path(archive_path.parent().unwrap_or(Path::new("")))?,
+			name: c_string(&file_path.file_stem().unwrap_or_default().to_string_lossy()),
+			ext: c_string(&ext),
+			data,
+			file_in_archive: true,
+			persistent_data: requirements.persistent_data(&ext),
+			_temp_dir: temp_dir,
+		})
+	}
+
+	/// Frees the content data once the core has loaded the game, unless it asked us to keep it around.
+	pub fn release_data(&mut self) {
+		if !self.persistent_data {
+			self.data = None;
+		}
+	}
+
+	/// The content file (or archive containing it) on disk.
+	pub fn source_path(&self) -> &Path {
+		&self.source_path
+	}
+
+	/// Gets a [GameInfo] which can be given to the core. The pointers in it
+	/// are only valid as long as this [LoadedContent] is.
+	pub fn game_info(&self) -> GameInfo {
+		let (data, size) = match self.data.as_ref() {
+			Some(data) => (data.as_ptr() as *const ffi::c_void, data.len()),
+			None => (std::ptr::null(), 0),
+		};
+
+		GameInfo {
+			path: self.path.as_ptr(),
+			data,
+			size,
+			meta: std::ptr::null(),
+		}
+	}
+
+	/// Gets a [GameInfoExt] which can be given to the core. The pointers in it
+	/// are only valid as long as this [LoadedContent] is.
+	pub fn game_info_ext(&self) -> GameInfoExt {
+		let game_info = self.game_info();
+
+		let opt_ptr = |string: &Option<CString>| match string {
+			Some(string) => string.as_ptr(),
+			None => std::ptr::null(),
+		};
+
+		GameInfoExt {
+			full_path: opt_ptr(&self.full_path),
+			archive_path: opt_ptr(&self.archive_path),
+			archive_file: opt_ptr(&self.archive_file),
+			dir: self.dir.as_ptr(),
+			name: self.name.as_ptr(),
+			ext: self.ext.as_ptr(),
+			meta: std::ptr::null(),
+			data: game_info.data,
+			size: game_info.size,
+			file_in_archive: self.file_in_archive,
+			persistent_data: self.persistent_data,
+		}
+	}
+}
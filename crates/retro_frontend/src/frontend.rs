@@ -1,7 +1,22 @@
+use crate::audio::{AudioConfig, AudioPipeline};
+use crate::cheats::{self, Cheat};
+use crate::content::{self, ContentInfoOverride, ContentRequirements, LoadedContent};
 use crate::input_devices::InputDevice;
 use crate::libretro_callbacks;
-use crate::libretro_core_variable::CoreVariable;
+use crate::libretro_core_variable::{CoreVariable, CoreVariableCategory};
+use crate::libretro_input_info::{ControllerType, InputDescriptor};
+use crate::libretro_subsystem::Subsystem;
+use crate::libretro_sys_new::{
+	CoreOptionsUpdateDisplayCallbackFn, DiskControlExtCallback, GameInfoExt, GetImageLabelFn,
+	ThrottleState, RETRO_THROTTLE_FAST_FORWARD, RETRO_THROTTLE_FRAME_STEPPING, RETRO_THROTTLE_NONE,
+	RETRO_THROTTLE_REWINDING, RETRO_THROTTLE_SLOW_MOTION,
+};
+use crate::libretro_vfs::{HostVfs, VfsBackend};
+use crate::movie::{Movie, MoviePlayer, MovieRecorder};
+use crate::pacer::Speed;
 use crate::result::{Error, Result};
+use crate::rewind::{RewindBuffer, RewindConfig};
+use crate::util;
 use ffi::CString;
 use libloading::Library;
 use libretro_sys::*;
@@ -9,10 +24,10 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::ffi;
 use std::os::unix::ffi::OsStrExt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::{fs, mem::MaybeUninit};
 
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 /// The currently running frontend.
 ///
@@ -33,22 +48,57 @@ pub trait FrontendInterface {
 	fn video_update(&mut self, slice: &[u32], pitch: u32);
 
 	/// Called when video is updated and the core is using HW OpenGL rendering.
-	fn video_update_gl(&mut self);
+	/// The frame is `width` by `height` pixels.
+	fn video_update_gl(&mut self, width: u32, height: u32);
 
 	/// Called when resize occurs.
 	fn video_resize(&mut self, width: u32, height: u32);
 
+	/// Called with interleaved stereo audio. `size` is the amount of stereo frames in `slice`.
+	/// If an [AudioConfig] has been set, this is always a chunk of the configured size and rate,
+	/// and a core which doesn't say what rate its audio is at won't have any of its audio given.
 	// TODO(lily): This should probably return the amount of consumed frames,
 	// as in some cases that *might* differ?
 	fn audio_sample(&mut self, slice: &[i16], size: usize);
 
-	/// Called to poll input
-	fn input_poll(&mut self);
+	/// Called to poll input. This is the time to update the state of plugged in input devices.
+	fn input_poll(&mut self, devices: &mut HashMap<u32 /* port */, Box<dyn InputDevice>>);
 
-	/// Initalize hardware accelerated rendering using OpenGL.
-	/// If this returns [Option::None], then it is assumed that
-	/// OpenGL initalization has failed.
-	fn hw_gl_init(&mut self) -> Option<HwGlInitData>;
+	/// Initalize hardware accelerated rendering using OpenGL, with a framebuffer
+	/// of `width` by `height` pixels. If this returns [Option::None], then it is
+	/// assumed that OpenGL initalization has failed.
+	fn hw_gl_init(&mut self, width: u32, height: u32) -> Option<HwGlInitData>;
+
+	/// Gets the ID of the OpenGL framebuffer HW OpenGL cores should render to.
+	fn hw_gl_framebuffer(&mut self) -> u32;
+}
+
+/// Directories the frontend uses. Any directories which do not exist
+/// will be created when the frontend is created.
+#[derive(Clone, Debug)]
+pub struct FrontendConfig {
+	/// The "system" directory. Used for BIOS roms.
+	pub system_directory: PathBuf,
+
+	/// The base save directory. Each core gets its own subdirectory in here.
+	pub save_directory: PathBuf,
+
+	/// The config directory. Stores configuration for each core.
+	pub config_directory: PathBuf,
+
+	/// The states directory. Stores save states for each core.
+	pub states_directory: PathBuf,
+}
+
+impl Default for FrontendConfig {
+	fn default() -> Self {
+		Self {
+			system_directory: "system".into(),
+			save_directory: "save".into(),
+			config_directory: "config".into(),
+			states_directory: "states".into(),
+		}
+	}
 }
 
 /// Per-core settings
@@ -83,31 +133,111 @@ pub struct Frontend {
 	pub(crate) fb_height: u32,
 	pub(crate) fb_pitch: u32,
 
-	// HW OpenGL FBO id.
-	pub(crate) gl_fbo_id: u32,
+	/// Directories given to us by the user.
+	pub(crate) config: FrontendConfig,
 
-	/// The "system" directory. Used for BIOS roms.
+	/// The "system" directory, as given to the core. Used for BIOS roms.
 	pub(crate) system_directory: CString,
 
-	/// The save directory. Used for saves. (TODO: Should make this per-core!!!)
+	/// The save directory of the current core, as given to the core. Used for saves.
 	pub(crate) save_directory: CString,
 
-	/// The config directory. Stores configuration for each core.
-	pub(crate) config_directory: String,
+	/// The path of the currently loaded game, if any.
+	pub(crate) game_path: Option<PathBuf>,
+
+	/// The currently loaded content, with one entry per piece of content the core was given.
+	/// Optional subsystem content may be left out. This owns everything the core was given when loading the game.
+	pub(crate) content: Vec<Option<LoadedContent>>,
+
+	/// Extended game info given to cores which ask for it, one per piece of content. This points into the above.
+	pub(crate) game_info_ext: Vec<GameInfoExt>,
+
+	/// Subsystems (special ways of loading games) the core supports.
+	pub(crate) subsystems: Vec<Subsystem>,
+
+	/// What the core says each button and axis does.
+	pub(crate) input_descriptors: Vec<InputDescriptor>,
+
+	/// The controller types the core supports, indexed by port.
+	pub(crate) controller_info: Vec<Vec<ControllerType>>,
+
+	/// Set if the core can run without any content.
+	pub(crate) supports_no_game: bool,
+
+	/// Per-extension content loading overrides the core gave us.
+	pub(crate) content_overrides: HashMap<String, ContentInfoOverride>,
+
+	/// The core's disk control interface, if it has one.
+	pub(crate) disk_control: Option<DiskControlExtCallback>,
+
+	/// Disk images which were appended to the core. These own what the core was given.
+	pub(crate) disk_images: Vec<LoadedContent>,
+
+	/// Labels for disk images we know of, by disk index.
+	pub(crate) disk_labels: HashMap<u32, String>,
+
+	/// Ordered list of cheats. Enabled cheats are applied to the core in this order.
+	pub(crate) cheats: Vec<Cheat>,
 
 	/// Hashmap of core variables.
 	pub(crate) variables: HashMap<String, CoreVariable>,
 
+	/// Set when a variable has been changed, and the core hasn't been told yet.
+	pub(crate) variables_updated: bool,
+
+	/// Hashmap of core variable categories. (Only used by cores using core options v2)
+	pub(crate) variable_categories: HashMap<String, CoreVariableCategory>,
+
+	/// Callback the core wants called when variables change, so it can update their visibility.
+	pub(crate) variables_update_display_callback: Option<CoreOptionsUpdateDisplayCallbackFn>,
+
+	/// The filesystem cores using the libretro VFS interface see.
+	pub(crate) vfs: Box<dyn VfsBackend>,
+
+	/// Takes audio from the core and gives it to the interface.
+	pub(crate) audio: AudioPipeline,
+
+	/// Set while emulation is paused. [Frontend::run_frame] does nothing while paused,
+	/// unless a single frame step has been requested.
+	pub(crate) paused: bool,
+	pub(crate) step_pending: bool,
+
+	/// How fast emulation is meant to be running.
+	pub(crate) speed: Speed,
+
+	/// Snapshots to rewind through, if rewinding is enabled.
+	pub(crate) rewind: Option<RewindBuffer>,
+
+	/// Set while running a frame after rewinding.
+	pub(crate) rewinding: bool,
+
+	/// The movie being recorded, if any.
+	pub(crate) movie_recorder: Option<MovieRecorder>,
+
+	/// The movie being replayed, if any.
+	pub(crate) movie_player: Option<MoviePlayer>,
+
 	/// Hashmap of connected input devices.
-	pub(crate) input_devices: HashMap<u32 /* port */, *mut dyn InputDevice>,
+	pub(crate) input_devices: HashMap<u32 /* port */, Box<dyn InputDevice>>,
 
-	pub(crate) interface: *mut dyn FrontendInterface,
+	pub(crate) interface: Box<dyn FrontendInterface>,
 }
 
 impl Frontend {
-	/// Creates a new boxed frontend instance. Note that the returned [Box]
-	/// must be held until this frontend is no longer used.
-	pub fn new(interface: *mut dyn FrontendInterface) -> Box<Self> {
+	/// Creates a new boxed frontend instance, which calls the given interface.
+	pub fn new(interface: Box<dyn FrontendInterface>, config: FrontendConfig) -> Result<Box<Self>> {
+		for directory in [
+			&config.system_directory,
+			&config.save_directory,
+			&config.config_directory,
+			&config.states_directory,
+		] {
+			fs::create_dir_all(directory)?;
+		}
+
+		let system_directory = CString::new(config.system_directory.as_os_str().as_bytes())
+			.map_err(|_| Error::InvalidPath(config.system_directory.clone()))?;
+
 		let mut boxed = Box::new(Self {
 			core_api: None,
 			core_library: None,
@@ -123,19 +253,48 @@ impl Frontend {
 			fb_width: 0,
 			fb_height: 0,
 			fb_pitch: 0,
-			gl_fbo_id: 0,
 
-			// TODO: We should let callers set these, probably.
-			// For now, this is probably fine.
-			system_directory: CString::new("system").unwrap(),
-			save_directory: CString::new("save").unwrap(),
-			config_directory: "config".into(),
+			system_directory,
+			// This is set once we know what core is loaded.
+			save_directory: CString::default(),
+			config,
+
+			game_path: None,
+			content: Vec::new(),
+			game_info_ext: Vec::new(),
+			subsystems: Vec::new(),
+			input_descriptors: Vec::new(),
+			controller_info: Vec::new(),
+			supports_no_game: false,
+			content_overrides: HashMap::new(),
+
+			disk_control: None,
+			disk_images: Vec::new(),
+			disk_labels: HashMap::new(),
+
+			cheats: Vec::new(),
 
 			variables: HashMap::new(),
+			variables_updated: false,
+			variable_categories: HashMap::new(),
+			variables_update_display_callback: None,
+
+			vfs: Box::new(HostVfs),
+
+			audio: AudioPipeline::new(),
+
+			paused: false,
+			step_pending: false,
+			speed: Speed::Normal,
+
+			rewind: None,
+			rewinding: false,
+			movie_recorder: None,
+			movie_player: None,
 
 			input_devices: HashMap::new(),
 
-			interface: interface,
+			interface,
 		});
 
 		// Assign to the global frontend pointer
@@ -144,7 +303,7 @@ impl Frontend {
 			FRONTEND = &mut *boxed as *mut Frontend;
 		}
 
-		boxed
+		Ok(boxed)
 	}
 
 	pub fn core_loaded(&self) -> bool {
@@ -152,45 +311,57 @@ impl Frontend {
 		self.core_library.is_some() && self.core_api.is_some()
 	}
 
-	/// Plugs in an input device to the specified port.
-	pub fn plug_input_device(&mut self, port: u32, device: *mut dyn InputDevice) {
+	/// Sets the filesystem that cores using the libretro VFS interface will see.
+	/// By default, cores get unrestricted access to the host filesystem.
+	///
+	/// This should be done before loading a core, as files the core
+	/// already has open will continue to use the old backend.
+	pub fn set_vfs_backend(&mut self, backend: Box<dyn VfsBackend>) {
+		self.vfs = backend;
+	}
+
+	/// Plugs in an input device to the specified port, replacing any device already plugged into it.
+	/// Devices stay plugged in when the core is changed.
+	pub fn plug_input_device(&mut self, port: u32, device: Box<dyn InputDevice>) {
 		if self.core_loaded() {
 			let core_api = self.core_api.as_mut().unwrap();
 
 			unsafe {
-				(core_api.retro_set_controller_port_device)(port, (*device).device_type());
-			}
-
-			if !self.input_devices.contains_key(&port) {
-				self.input_devices.insert(port, device);
-			} else {
-				(*self.input_devices.get_mut(&port).unwrap()) = device;
+				(core_api.retro_set_controller_port_device)(port, device.device_type());
 			}
 		}
+
+		self.input_devices.insert(port, device);
 	}
 
-	/// Unplugs a input device from the given port.
-	pub fn unplug_input_device(&mut self, port: u32) {
+	/// Unplugs a input device from the given port, giving it back.
+	pub fn unplug_input_device(&mut self, port: u32) -> Option<Box<dyn InputDevice>> {
 		if self.core_loaded() {
 			let core_api = self.core_api.as_mut().unwrap();
 
 			unsafe {
 				(core_api.retro_set_controller_port_device)(port, DEVICE_NONE);
 			}
-
-			if !self.input_devices.contains_key(&port) {
-				self.input_devices.remove(&port);
-			}
 		}
+
+		self.input_devices.remove(&port)
+	}
+
+	/// Gets the input device plugged into the given port.
+	pub fn get_input_device(&mut self, port: u32) -> Option<&mut (dyn InputDevice + 'static)> {
+		self.input_devices
+			.get_mut(&port)
+			.map(|device| device.as_mut())
 	}
 
-	fn get_config_file_path(&mut self) -> Result<String> {
+	/// Gets the library name of the currently loaded core.
+	pub(crate) fn get_core_name(&mut self) -> Result<String> {
 		let system_info = self.get_system_info()?;
 
 		// SAFETY: libretro declares that the pointers inside of the SystemInfo structure
 		// must always point to valid constant data. If it doesn't then other frontends
 		// would probably blow up too.
-		let path = unsafe {
+		let name = unsafe {
 			#[cfg(debug_assertions)]
 			assert!(
 				!system_info.library_name.is_null(),
@@ -198,56 +369,163 @@ impl Frontend {
 			);
 
 			let c_name = ffi::CStr::from_ptr(system_info.library_name);
-
-			format!(
-				"{}/{}.toml",
-				self.config_directory,
-				c_name.to_str().expect("ughh")
-			)
+			c_name.to_str()?.to_string()
 		};
 
+		Ok(name)
+	}
+
+	/// Gets the name saves for the current game are named after. This is the name of the content,
+	/// or the name of the core if it's running without any.
+	fn get_game_name(&mut self) -> Result<String> {
+		match self.game_path.as_ref() {
+			Some(path) => Ok(path
+				.file_stem()
+				.unwrap_or_default()
+				.to_string_lossy()
+				.into_owned()),
+			None if self.game_loaded => self.get_core_name(),
+			None => Err(Error::GameNotLoaded),
+		}
+	}
+
+	/// Creates the save directory for the current core. Each core gets its own save directory,
+	/// so that saves from different cores for the same game don't clobber each other.
+	fn create_core_save_directory(&mut self) -> Result<()> {
+		let mut save_directory = self.config.save_directory.clone();
+		save_directory.push(self.get_core_name()?);
+		fs::create_dir_all(&save_directory)?;
+
+		self.save_directory = CString::new(save_directory.as_os_str().as_bytes())
+			.map_err(|_| Error::InvalidPath(save_directory.clone()))?;
+		Ok(())
+	}
+
+	fn get_config_file_path(&mut self) -> Result<PathBuf> {
+		let core_name = self.get_core_name()?;
+
+		let mut path = self.config.config_directory.clone();
+		path.push(format!("{core_name}.toml"));
 		Ok(path)
 	}
 
-	// TODO: make this a bit less janky (and use Results)
+	/// Gets the path of the file backing the given save state slot for the currently loaded game.
+	/// States are stored per-core, as `<states directory>/<core name>/<rom name>.state<slot>`.
+	fn get_state_file_path(&mut self, slot: u32) -> Result<PathBuf> {
+		let core_name = self.get_core_name()?;
+		let rom_name = self.get_game_name()?;
 
-	pub fn load_settings(&mut self) {
-		let path_string = self
-			.get_config_file_path()
-			.expect("Could not get config file path");
-		let path: &Path = path_string.as_ref();
+		let mut path = self.config.states_directory.clone();
+		path.push(core_name);
+		path.push(format!("{rom_name}.state{slot}"));
+		Ok(path)
+	}
+
+	/// Gets the core variable with the given key.
+	pub fn get_variable(&self, key: &str) -> Option<&CoreVariable> {
+		self.variables.get(key)
+	}
+
+	/// Gets all of the current core's variables.
+	pub fn get_variables(&self) -> &HashMap<String, CoreVariable> {
+		&self.variables
+	}
+
+	/// Gets all of the current core's variable categories.
+	pub fn get_variable_categories(&self) -> &HashMap<String, CoreVariableCategory> {
+		&self.variable_categories
+	}
 
-		match path.try_exists() {
-			Ok(exists) => {
-				if exists {
-					let data = fs::read_to_string(path).expect("Could not read config");
-					let config =
-						toml::from_str::<CoreSettingsFile>(&data).expect("Could not parse config");
-					self.variables = config.variables;
+	/// Replaces the current core's variables with a new set the core has given us,
+	/// and loads the user's settings on top of them.
+	pub(crate) fn set_core_variables(&mut self, variables: HashMap<String, CoreVariable>) {
+		self.variables = variables;
+
+		// Load settings. If that fails, we just stick with the defaults.
+		if let Err(err) = self.load_settings() {
+			error!("Could not load core settings, using defaults: {err}");
+		}
+	}
+
+	/// Changes the value of a core variable. The core will be told about the change
+	/// the next time it checks for variable updates (usually during [Frontend::run_frame]).
+	///
+	/// This does not persist the change; call [Frontend::save_settings] to do so.
+	pub fn set_variable(&mut self, key: &str, value: &str) -> Result<()> {
+		let variable = match self.variables.get_mut(key) {
+			Some(variable) => variable,
+			None => return Err(Error::UnknownCoreVariable(key.to_string())),
+		};
+
+		if !variable.choices.iter().any(|choice| choice == value) {
+			return Err(Error::InvalidCoreVariableValue {
+				key: key.to_string(),
+				value: value.to_string(),
+			});
+		}
+
+		variable.set_value(value);
+		self.variables_updated = true;
+
+		// Let the core update which variables should be visible, now that this one changed.
+		if let Some(callback) = self.variables_update_display_callback {
+			unsafe {
+				callback();
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Loads the current core's settings from disk. If no settings file exists yet,
+	/// the core's initial settings are saved to disk instead.
+	pub fn load_settings(&mut self) -> Result<()> {
+		let path = self.get_config_file_path()?;
+
+		if !path.try_exists()? {
+			// Save the core's initial settings to disk
+			return self.save_settings();
+		}
+
+		let data = fs::read_to_string(&path)?;
+		let config = toml::from_str::<CoreSettingsFile>(&data)?;
+
+		// Only take the values from the settings file; the core's definitions
+		// (choices, defaults, etc.) are authoritative, since they might have changed.
+		for (key, saved) in config.variables {
+			let value = match saved.value {
+				Some(value) => value,
+				None => continue,
+			};
+
+			if let Some(variable) = self.variables.get_mut(&key) {
+				if variable.choices.contains(&value) {
+					variable.set_value(&value);
 				} else {
-					// Save the core's initial settings to disk
-					self.save_settings();
+					error!("Ignoring invalid saved value \"{value}\" for core variable {key}");
 				}
 			}
-			Err(e) => {
-				error!("Can't seem to read {}: {}", path.display(), e);
-			}
 		}
+
+		self.variables_updated = true;
+
+		info!("Loaded settings from {}", path.display());
+		Ok(())
 	}
 
-	pub fn save_settings(&mut self) {
-		let path = self
-			.get_config_file_path()
-			.expect("Could not get config file path");
+	/// Saves the current core's settings to disk.
+	pub fn save_settings(&mut self) -> Result<()> {
+		let path = self.get_config_file_path()?;
 
 		let settings = CoreSettingsFile {
 			variables: self.variables.clone(),
 		};
 
-		let string = toml::to_string(&settings).expect("Could not serialize settings");
-		fs::write(path.clone(), string).expect("Could not save settings to disk");
+		let string = toml::to_string(&settings)?;
+		fs::write(&path, string)?;
 
-		info!("Saved settings to {path}");
+		info!("Saved settings to {}", path.display());
+		Ok(())
 	}
 
 	pub fn load_core<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
@@ -316,6 +594,13 @@ impl Frontend {
 			self.core_library = Some(lib);
 			self.core_api = Some(core_api);
 
+			if let Err(err) = self.create_core_save_directory() {
+				self.core_api = None;
+				self.core_library = None;
+				self.sys_info = None;
+				return Err(err);
+			}
+
 			let core_api_ref = self.core_api.as_ref().unwrap();
 
 			// Set required libretro callbacks before calling libretro_init.
@@ -332,10 +617,16 @@ impl Frontend {
 			(core_api_ref.retro_set_video_refresh)(libretro_callbacks::video_refresh_callback);
 			(core_api_ref.retro_set_input_poll)(libretro_callbacks::input_poll_callback);
 			(core_api_ref.retro_set_input_state)(libretro_callbacks::input_state_callback);
+			(core_api_ref.retro_set_audio_sample)(libretro_callbacks::audio_sample_callback);
 			(core_api_ref.retro_set_audio_sample_batch)(
 				libretro_callbacks::audio_sample_batch_callback,
 			);
 
+			// Tell the core about devices which were plugged in before it was loaded.
+			for (port, device) in &self.input_devices {
+				(core_api_ref.retro_set_controller_port_device)(*port, device.device_type());
+			}
+
 			info!("Core {} loaded", path.as_ref().display());
 		}
 
@@ -368,61 +659,293 @@ impl Frontend {
 
 		// FIXME: Do other various cleanup (when we need to do said cleanup)
 		self.av_info = None;
+		self.sys_info = None;
+		self.save_directory = CString::default();
+		self.content_overrides.clear();
+		self.disk_control = None;
+		self.subsystems.clear();
+		self.input_descriptors.clear();
+		self.controller_info.clear();
+		self.supports_no_game = false;
+
+		self.variables.clear();
+		self.variables_updated = false;
+		self.variable_categories.clear();
+		self.variables_update_display_callback = None;
 
 		self.fb_width = 0;
 		self.fb_height = 0;
 		self.fb_pitch = 0;
 
-		// disconnect all currently connected joypads
-		self.input_devices.clear();
-
 		Ok(())
 	}
 
+	/// Gets how the loaded core wants content to be given to it.
+	fn get_content_requirements(&mut self) -> Result<ContentRequirements> {
+		let system_info = self.get_system_info()?;
+
+		// SAFETY: The pointers in the system info are valid for as long as the core is loaded.
+		Ok(unsafe { ContentRequirements::from_system_info(&system_info, &self.content_overrides) })
+	}
+
+	/// Loads a game. `.m3u` playlists are supported for cores which use the disk control
+	/// interface but can't load playlists themselves: the first entry is loaded as the game,
+	/// and the rest are appended as additional disk images.
 	pub fn load_game<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
 		if !self.core_loaded() {
 			return Err(Error::CoreNotLoaded);
 		}
 
-		// I'm aware this is nasty but bleh
-		let slice = path.as_ref().as_os_str().as_bytes();
-		let path_string = CString::new(slice).expect("shouldn't fail");
-
-		let system_info = self.get_system_info()?;
+		let path = path.as_ref();
+		let requirements = self.get_content_requirements()?;
 
-		let core_api = self.core_api.as_ref().unwrap();
+		let playlist = if content::is_playlist(path)
+			&& !requirements.valid_extensions.iter().any(|ext| ext == "m3u")
+		{
+			Some(content::parse_m3u(path)?)
+		} else {
+			None
+		};
 
-		let mut gameinfo = GameInfo {
-			path: path_string.as_ptr(),
-			data: std::ptr::null(),
-			size: 0,
-			meta: std::ptr::null(),
+		let content_path = match playlist.as_ref() {
+			Some(playlist) => playlist[0].path.as_path(),
+			None => path,
 		};
 
-		// If the core does not need fullpath, then
-		// read the file data into a buffer we give to the core.
-		// This is pretty wasteful but works.
-		if !system_info.need_fullpath {
-			let contents = fs::read(path)?;
-			gameinfo.data = contents.as_ptr() as *const ffi::c_void;
-			gameinfo.size = contents.len();
+		let content = LoadedContent::load(content_path, &requirements)?;
 
-			unsafe {
-				if !(core_api.retro_load_game)(&gameinfo) {
-					return Err(Error::RomLoadFailed);
+		self.load_content(vec![Some(content)], |core_api, game_info| unsafe {
+			(core_api.retro_load_game)(&game_info[0])
+		})?;
+
+		if let Some(playlist) = playlist {
+			// Saves are named after the playlist, so they're shared between all of its disks.
+			self.game_path = Some(path.to_path_buf());
+			self.load_playlist_disks(playlist);
+		}
+
+		self.game_loaded_hook();
+		Ok(())
+	}
+
+	/// Starts a core which can run without any content.
+	pub fn load_no_game(&mut self) -> Result<()> {
+		if !self.core_loaded() {
+			return Err(Error::CoreNotLoaded);
+		}
+
+		if !self.supports_no_game {
+			return Err(Error::NoGameUnsupported);
+		}
+
+		self.load_content(Vec::new(), |core_api, _| unsafe {
+			(core_api.retro_load_game)(std::ptr::null())
+		})?;
+
+		self.game_loaded_hook();
+		Ok(())
+	}
+
+	/// Gets what the core says each button and axis does in the loaded game.
+	pub fn get_input_descriptors(&self) -> &[InputDescriptor] {
+		&self.input_descriptors[..]
+	}
+
+	/// Gets the description of a button or axis, e.g. "Jump". `device` is the base device type.
+	pub fn get_input_description(
+		&self,
+		port: u32,
+		device: u32,
+		index: u32,
+		id: u32,
+	) -> Option<&str> {
+		self.input_descriptors
+			.iter()
+			.find(|desc| {
+				desc.port == port && desc.device == device && desc.index == index && desc.id == id
+			})
+			.map(|desc| desc.description.as_str())
+	}
+
+	/// Gets the controller types the core supports on the given port. If this is empty,
+	/// the core didn't say, and any device can be plugged in.
+	pub fn get_controller_types(&self, port: u32) -> &[ControllerType] {
+		self.controller_info
+			.get(port as usize)
+			.map_or(&[], |types| &types[..])
+	}
+
+	/// Gets the subsystems (special ways of loading games) the loaded core supports.
+	pub fn get_subsystems(&self) -> &[Subsystem] {
+		&self.subsystems[..]
+	}
+
+	/// Loads a game using the subsystem with the given identifier (e.g. "sgb" for Super Game Boy).
+	/// Paths are given in the order the subsystem lists its content in. Trailing content which
+	/// the subsystem doesn't require can be left out.
+	pub fn load_game_special<P: AsRef<Path>>(&mut self, ident: &str, paths: &[P]) -> Result<()> {
+		if !self.core_loaded() {
+			return Err(Error::CoreNotLoaded);
+		}
+
+		let subsystem = self
+			.subsystems
+			.iter()
+			.find(|subsystem| subsystem.ident == ident)
+			.cloned()
+			.ok_or_else(|| Error::UnknownSubsystem(ident.to_string()))?;
+
+		if paths.len() > subsystem.roms.len() {
+			return Err(Error::InvalidSubsystemContent(format!(
+				"{} takes at most {} pieces of content, but {} were given",
+				subsystem.description,
+				subsystem.roms.len(),
+				paths.len()
+			)));
+		}
+
+		let mut content = Vec::with_capacity(subsystem.roms.len());
+
+		for (index, rom) in subsystem.roms.iter().enumerate() {
+			let path = match paths.get(index) {
+				Some(path) => path.as_ref(),
+				None if rom.required => {
+					return Err(Error::InvalidSubsystemContent(format!(
+						"{} requires {}",
+						subsystem.description, rom.description
+					)));
+				}
+				None => {
+					content.push(None);
+					continue;
 				}
+			};
 
-				self.game_loaded = true;
-				Ok(())
+			let requirements =
+				ContentRequirements::from_subsystem_rom(rom, &self.content_overrides);
+
+			if !requirements.accepts(path) {
+				return Err(Error::InvalidSubsystemContent(format!(
+					"{} cannot be loaded as {}",
+					path.display(),
+					rom.description
+				)));
 			}
-		} else {
-			unsafe {
-				if !(core_api.retro_load_game)(&gameinfo) {
-					return Err(Error::RomLoadFailed);
+
+			content.push(Some(LoadedContent::load(path, &requirements)?));
+		}
+
+		info!("Loading game using subsystem {}", subsystem.description);
+
+		self.load_content(content, |core_api, game_info| unsafe {
+			(core_api.retro_load_game_special)(subsystem.id, game_info.as_ptr(), game_info.len())
+		})?;
+
+		self.game_loaded_hook();
+		Ok(())
+	}
+
+	/// Gives loaded content to the core, using `load` to call the right load function.
+	fn load_content(
+		&mut self,
+		content: Vec<Option<LoadedContent>>,
+		load: impl FnOnce(&CoreAPI, &[GameInfo]) -> bool,
+	) -> Result<()> {
+		// The running game's content has to outlive it, so it has to be unloaded properly first.
+		if self.game_loaded {
+			self.unload_game()?;
+		}
+
+		let game_info: Vec<_> = content
+			.iter()
+			.map(|content| match content {
+				Some(content) => content.game_info(),
+				None => GameInfo {
+					path: std::ptr::null(),
+					data: std::ptr::null(),
+					size: 0,
+					meta: std::ptr::null(),
+				},
+			})
+			.collect();
+
+		// The content needs to be in place while loading, since the core can ask for its GameInfoExt.
+		self.content = content;
+		self.update_game_info_ext();
+
+		if !load(self.core_api.as_ref().unwrap(), &game_info) {
+			self.content.clear();
+			self.game_info_ext.clear();
+			return Err(Error::RomLoadFailed);
+		}
+
+		for content in self.content.iter_mut().flatten() {
+			content.release_data();
+		}
+
+		self.update_game_info_ext();
+
+		self.game_loaded = true;
+
+		// The first piece of content is the one saves are named after.
+		self.game_path = self
+			.content
+			.first()
+			.and_then(|content| content.as_ref())
+			.map(|content| content.source_path().to_path_buf());
+
+		Ok(())
+	}
+
+	/// Updates the [GameInfoExt]s given to the core to match the loaded content.
+	fn update_game_info_ext(&mut self) {
+		// The core may hold onto the pointer it was given, so this reuses the existing
+		// allocation when the amount of content hasn't changed.
+		self.game_info_ext.clear();
+		self.game_info_ext
+			.extend(self.content.iter().map(|content| match content {
+				Some(content) => content.game_info_ext(),
+				// SAFETY: GameInfoExt is only pointers, integers and booleans, which are all valid zeroed.
+				None => unsafe { MaybeUninit::zeroed().assume_init() },
+			}));
+	}
+
+	/// Things to do once a game has been loaded.
+	fn game_loaded_hook(&mut self) {
+		// AV info is only valid once a game has been loaded, so make sure it isn't stale.
+		self.av_info = None;
+		match self.get_av_info() {
+			Ok(av_info) => self.audio.set_timing(&av_info.timing),
+			Err(err) => error!("Could not get AV info: {err}"),
+		}
+
+		// Restore battery saves. A failure here shouldn't prevent the game from running.
+		if let Err(err) = self.load_save_ram() {
+			error!("Could not load save RAM: {err}");
+		}
+
+		self.apply_cheats();
+	}
+
+	/// Appends all but the first (already loaded) entry of a playlist as disk images.
+	fn load_playlist_disks(&mut self, playlist: Vec<content::PlaylistEntry>) {
+		if playlist.len() > 1 && self.disk_control.is_none() {
+			warn!("Core does not support disk control, so only the first disk of the playlist was loaded");
+		}
+
+		for (index, entry) in playlist.into_iter().enumerate() {
+			if index != 0 && self.disk_control.is_some() {
+				if let Err(err) = self.append_disk_image(&entry.path) {
+					error!(
+						"Could not append disk image {}: {err}",
+						entry.path.display()
+					);
+					continue;
 				}
+			}
 
-				self.game_loaded = true;
-				Ok(())
+			if let Some(label) = entry.label {
+				self.disk_labels.insert(index as u32, label);
 			}
 		}
 	}
@@ -432,19 +955,462 @@ impl Frontend {
 			return Err(Error::CoreNotLoaded);
 		}
 
-		let core_api = self.core_api.as_ref().unwrap();
-
 		if self.game_loaded {
+			// Write back battery saves before the core gets rid of them.
+			if let Err(err) = self.flush_save_ram() {
+				error!("Could not save save RAM: {err}");
+			}
+
+			let core_api = self.core_api.as_ref().unwrap();
+
 			unsafe {
 				(core_api.retro_unload_game)();
 			}
 
 			self.game_loaded = false;
+			self.game_path = None;
+			self.content.clear();
+			self.game_info_ext.clear();
+			self.disk_images.clear();
+			self.disk_labels.clear();
+			self.input_descriptors.clear();
+			self.audio.reset();
+
+			if let Some(rewind) = self.rewind.as_mut() {
+				rewind.clear();
+			}
+
+			self.movie_recorder = None;
+			self.stop_replay();
+		}
+
+		Ok(())
+	}
+
+	/// Gets the path of the battery save file for the currently loaded game.
+	/// Battery saves are stored as `<save directory>/<rom name>.srm`.
+	fn get_save_ram_file_path(&mut self) -> Result<PathBuf> {
+		let rom_name = self.get_game_name()?;
+
+		let mut path = PathBuf::from(ffi::OsStr::from_bytes(self.save_directory.as_bytes()));
+		path.push(format!("{rom_name}.srm"));
+		Ok(path)
+	}
+
+	/// Gets the battery-backed save RAM region the core exposes, if it has one.
+	fn get_save_ram(&mut self) -> Option<&mut [u8]> {
+		let core_api = self.core_api.as_ref()?;
+
+		// SAFETY: The core owns this memory, and it must remain valid until
+		// retro_unload_game() is called.
+		unsafe {
+			let data = (core_api.retro_get_memory_data)(MEMORY_SAVE_RAM);
+			let size = (core_api.retro_get_memory_size)(MEMORY_SAVE_RAM);
+
+			if data.is_null() || size == 0 {
+				return None;
+			}
+
+			Some(std::slice::from_raw_parts_mut(data as *mut u8, size))
+		}
+	}
+
+	/// Restores the battery save for the currently loaded game into the core's save RAM.
+	fn load_save_ram(&mut self) -> Result<()> {
+		let path = self.get_save_ram_file_path()?;
+
+		let save_ram = match self.get_save_ram() {
+			Some(save_ram) => save_ram,
+			None => return Ok(()),
+		};
+
+		if !path.try_exists()? {
+			return Ok(());
+		}
+
+		let data = fs::read(&path)?;
+
+		// Be lenient about size mismatches; some cores change their save RAM size
+		// between versions, and other frontends handle this the same way.
+		let len = data.len().min(save_ram.len());
+		save_ram[..len].copy_from_slice(&data[..len]);
+
+		info!("Loaded save RAM from {}", path.display());
+		Ok(())
+	}
+
+	/// Writes the core's battery save RAM for the currently loaded game to disk.
+	/// This is done automatically when the game is unloaded, but should also be called
+	/// periodically so that saves are not lost if the process goes away unexpectedly.
+	pub fn flush_save_ram(&mut self) -> Result<()> {
+		if !self.core_loaded() {
+			return Err(Error::CoreNotLoaded);
+		}
+
+		let path = self.get_save_ram_file_path()?;
+
+		let save_ram = match self.get_save_ram() {
+			Some(save_ram) => save_ram,
+			None => return Ok(()),
+		};
+
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		fs::write(&path, save_ram)?;
+
+		info!("Saved save RAM to {}", path.display());
+		Ok(())
+	}
+
+	/// Gets the list of cheats.
+	pub fn get_cheats(&self) -> &[Cheat] {
+		&self.cheats[..]
+	}
+
+	/// Adds a cheat to the end of the cheat list.
+	pub fn add_cheat(&mut self, cheat: Cheat) {
+		self.cheats.push(cheat);
+		self.apply_cheats();
+	}
+
+	/// Removes the cheat at the given index.
+	pub fn remove_cheat(&mut self, index: usize) -> Result<Cheat> {
+		if index >= self.cheats.len() {
+			return Err(Error::InvalidCheatIndex(index));
+		}
+
+		let cheat = self.cheats.remove(index);
+		self.apply_cheats();
+		Ok(cheat)
+	}
+
+	/// Enables or disables the cheat at the given index.
+	pub fn set_cheat_enabled(&mut self, index: usize, enabled: bool) -> Result<()> {
+		match self.cheats.get_mut(index) {
+			Some(cheat) => cheat.enabled = enabled,
+			None => return Err(Error::InvalidCheatIndex(index)),
+		}
+
+		self.apply_cheats();
+		Ok(())
+	}
+
+	/// Removes all cheats.
+	pub fn clear_cheats(&mut self) {
+		self.cheats.clear();
+		self.apply_cheats();
+	}
+
+	/// Imports cheats from a RetroArch `.cht` file, appending them to the cheat list.
+	pub fn load_cheat_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+		let data = fs::read_to_string(path.as_ref())?;
+		let mut cheats = cheats::parse_cht(&data)?;
+
+		info!(
+			"Loaded {} cheats from {}",
+			cheats.len(),
+			path.as_ref().display()
+		);
+
+		self.cheats.append(&mut cheats);
+		self.apply_cheats();
+		Ok(())
+	}
+
+	/// Resets the core's cheats and applies all the enabled cheats in the cheat list.
+	fn apply_cheats(&mut self) {
+		if !self.core_loaded() || !self.game_loaded {
+			return;
+		}
+
+		let core_api = self.core_api.as_ref().unwrap();
+
+		unsafe {
+			(core_api.retro_cheat_reset)();
+		}
+
+		for (index, cheat) in self.cheats.iter().enumerate() {
+			if !cheat.enabled {
+				continue;
+			}
+
+			let code = match CString::new(cheat.code.as_bytes()) {
+				Ok(code) => code,
+				Err(_) => {
+					error!("Cheat \"{}\" has an invalid code", cheat.description);
+					continue;
+				}
+			};
+
+			unsafe {
+				(core_api.retro_cheat_set)(index as u32, true, code.as_ptr());
+			}
+		}
+	}
+
+	fn get_disk_control(&self) -> Result<DiskControlExtCallback> {
+		self.disk_control.ok_or(Error::DiskControlUnsupported)
+	}
+
+	/// Returns true if the loaded core supports swapping disks.
+	pub fn disk_control_supported(&self) -> bool {
+		self.disk_control.is_some()
+	}
+
+	/// Gets the number of disk images the core knows about.
+	pub fn get_disk_count(&self) -> Result<u32> {
+		let disk_control = self.get_disk_control()?;
+		Ok(unsafe { (disk_control.get_num_images)() })
+	}
+
+	/// Gets the index of the currently selected disk, or [None] if no disk is selected.
+	pub fn get_disk_index(&self) -> Result<Option<u32>> {
+		let disk_control = self.get_disk_control()?;
+
+		unsafe {
+			let index = (disk_control.get_image_index)();
+			if index >= (disk_control.get_num_images)() {
+				return Ok(None);
+			}
+
+			Ok(Some(index))
+		}
+	}
+
+	/// Returns true if the virtual disk tray is ejected.
+	pub fn is_disk_ejected(&self) -> Result<bool> {
+		let disk_control = self.get_disk_control()?;
+		Ok(unsafe { (disk_control.get_eject_state)() })
+	}
+
+	fn set_disk_eject_state(&mut self, ejected: bool) -> Result<()> {
+		let disk_control = self.get_disk_control()?;
+
+		if !unsafe { (disk_control.set_eject_state)(ejected) } {
+			return Err(Error::DiskControlFailed);
 		}
 
 		Ok(())
 	}
 
+	/// Ejects the virtual disk tray, so that another disk can be selected.
+	pub fn eject_disk(&mut self) -> Result<()> {
+		self.set_disk_eject_state(true)
+	}
+
+	/// Closes the virtual disk tray, inserting the selected disk.
+	pub fn insert_disk(&mut self) -> Result<()> {
+		self.set_disk_eject_state(false)
+	}
+
+	/// Selects the disk at the given index. The disk tray must be ejected first.
+	pub fn set_disk_index(&mut self, index: u32) -> Result<()> {
+		let disk_control = self.get_disk_control()?;
+
+		if index >= self.get_disk_count()? {
+			return Err(Error::InvalidDiskIndex(index));
+		}
+
+		if !self.is_disk_ejected()? {
+			return Err(Error::DiskNotEjected);
+		}
+
+		if !unsafe { (disk_control.set_image_index)(index) } {
+			return Err(Error::DiskControlFailed);
+		}
+
+		Ok(())
+	}
+
+	/// Swaps to the disk at the given index, ejecting and re-inserting the disk tray as needed.
+	pub fn swap_disk(&mut self, index: u32) -> Result<()> {
+		if index >= self.get_disk_count()? {
+			return Err(Error::InvalidDiskIndex(index));
+		}
+
+		if !self.is_disk_ejected()? {
+			self.eject_disk()?;
+		}
+
+		self.set_disk_index(index)?;
+		self.insert_disk()
+	}
+
+	/// Appends a disk image to the core's disk list. This does not change the selected disk.
+	pub fn append_disk_image<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+		let disk_control = self.get_disk_control()?;
+
+		if !self.game_loaded {
+			return Err(Error::GameNotLoaded);
+		}
+
+		let requirements = self.get_content_requirements()?;
+		let content = LoadedContent::load(path.as_ref(), &requirements)?;
+
+		// The tray needs to be ejected to replace disk images.
+		let was_ejected = self.is_disk_ejected()?;
+		if !was_ejected {
+			self.eject_disk()?;
+		}
+
+		let result = unsafe {
+			let index = (disk_control.get_num_images)();
+
+			if (disk_control.add_image_index)()
+				&& (disk_control.replace_image_index)(index, &content.game_info())
+			{
+				Ok(index)
+			} else {
+				Err(Error::DiskControlFailed)
+			}
+		};
+
+		if !was_ejected {
+			self.insert_disk()?;
+		}
+
+		let index = result?;
+		if let Some(stem) = path.as_ref().file_stem() {
+			self.disk_labels
+				.insert(index, stem.to_string_lossy().into_owned());
+		}
+
+		self.disk_images.push(content);
+		Ok(())
+	}
+
+	/// Gets a label for the disk at the given index. Labels given by the core are preferred,
+	/// then labels from playlists, then the file name of the disk image.
+	pub fn get_disk_label(&self, index: u32) -> Result<Option<String>> {
+		let disk_control = self.get_disk_control()?;
+
+		if index >= self.get_disk_count()? {
+			return Err(Error::InvalidDiskIndex(index));
+		}
+
+		let read_string = |func: Option<GetImageLabelFn>| -> Option<String> {
+			let mut buffer = [0 as ffi::c_char; 4096];
+
+			unsafe {
+				if !func?(index, buffer.as_mut_ptr(), buffer.len()) {
+					return None;
+				}
+
+				// Make sure the string is terminated, even if the core wasn't careful.
+				buffer[buffer.len() - 1] = 0;
+				util::string_from_c(buffer.as_ptr()).filter(|string| !string.is_empty())
+			}
+		};
+
+		if let Some(label) = read_string(disk_control.get_image_label) {
+			return Ok(Some(label));
+		}
+
+		if let Some(label) = self.disk_labels.get(&index) {
+			return Ok(Some(label.clone()));
+		}
+
+		// Fall back to the file name of the disk image, if the core will tell us its path.
+		Ok(read_string(disk_control.get_image_path).map(|path| {
+			Path::new(&path)
+				.file_stem()
+				.map(|stem| stem.to_string_lossy().into_owned())
+				.unwrap_or(path)
+		}))
+	}
+
+	/// Gets labels for all the disks the core knows about.
+	pub fn get_disk_labels(&self) -> Result<Vec<Option<String>>> {
+		(0..self.get_disk_count()?)
+			.map(|index| self.get_disk_label(index))
+			.collect()
+	}
+
+	/// Serializes the state of the currently running game into an owned buffer.
+	pub fn save_state(&mut self) -> Result<Vec<u8>> {
+		if !self.core_loaded() {
+			return Err(Error::CoreNotLoaded);
+		}
+
+		if !self.game_loaded {
+			return Err(Error::GameNotLoaded);
+		}
+
+		let core_api = self.core_api.as_ref().unwrap();
+
+		unsafe {
+			let size = (core_api.retro_serialize_size)();
+			if size == 0 {
+				return Err(Error::SaveStatesUnsupported);
+			}
+
+			// libretro_sys declares retro_serialize() as returning nothing, but
+			// the libretro API actually returns a bool to signal if serialization succeeded.
+			let retro_serialize: unsafe extern "C" fn(*mut ffi::c_void, usize) -> bool =
+				std::mem::transmute(core_api.retro_serialize);
+
+			let mut buffer = vec![0u8; size];
+			if !retro_serialize(buffer.as_mut_ptr() as *mut ffi::c_void, buffer.len()) {
+				return Err(Error::StateSaveFailed);
+			}
+
+			Ok(buffer)
+		}
+	}
+
+	/// Loads a state previously returned by [Frontend::save_state] into the currently running game.
+	pub fn load_state(&mut self, data: &[u8]) -> Result<()> {
+		if !self.core_loaded() {
+			return Err(Error::CoreNotLoaded);
+		}
+
+		if !self.game_loaded {
+			return Err(Error::GameNotLoaded);
+		}
+
+		let core_api = self.core_api.as_ref().unwrap();
+
+		unsafe {
+			if (core_api.retro_serialize_size)() == 0 {
+				return Err(Error::SaveStatesUnsupported);
+			}
+
+			if !(core_api.retro_unserialize)(data.as_ptr() as *const ffi::c_void, data.len()) {
+				return Err(Error::StateLoadFailed);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Saves the state of the currently running game to the given slot on disk.
+	pub fn save_state_to_slot(&mut self, slot: u32) -> Result<()> {
+		let path = self.get_state_file_path(slot)?;
+		let state = self.save_state()?;
+
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)?;
+		}
+
+		fs::write(&path, state)?;
+
+		info!("Saved state to {}", path.display());
+		Ok(())
+	}
+
+	/// Loads the state stored in the given slot on disk into the currently running game.
+	pub fn load_state_from_slot(&mut self, slot: u32) -> Result<()> {
+		let path = self.get_state_file_path(slot)?;
+		let state = fs::read(&path)?;
+
+		self.load_state(&state[..])?;
+
+		info!("Loaded state from {}", path.display());
+		Ok(())
+	}
+
 	pub fn get_av_info(&mut self) -> Result<SystemAvInfo> {
 		if !self.core_loaded() {
 			return Err(Error::CoreNotLoaded);
@@ -493,24 +1459,270 @@ impl Frontend {
 		(self.fb_width, self.fb_height)
 	}
 
-	pub fn set_gl_fbo(&mut self, id: u32) {
-		self.gl_fbo_id = id;
-	}
-
 	pub fn reset(&mut self) {
 		let core_api = self.core_api.as_ref().unwrap();
 
 		unsafe {
 			(core_api.retro_reset)();
 		}
+
+		// Some cores clear cheats on reset, so reapply them.
+		self.apply_cheats();
 	}
 
 	pub fn run_frame(&mut self) {
+		if self.paused {
+			if !self.step_pending {
+				return;
+			}
+
+			self.step_pending = false;
+		}
+
+		if let Some(player) = self.movie_player.as_mut() {
+			if !player.begin_frame() {
+				info!("Movie replay finished");
+				self.stop_replay();
+			}
+		}
+
+		self.run_core_frame();
+
+		if let Some(recorder) = self.movie_recorder.as_mut() {
+			recorder.end_frame();
+		}
+
+		self.take_rewind_snapshot();
+	}
+
+	fn run_core_frame(&mut self) {
 		let core_api = self.core_api.as_ref().unwrap();
 
 		unsafe {
 			(core_api.retro_run)();
+			self.audio.end_frame(self.interface.as_mut());
+		}
+	}
+
+	/// Takes a rewind snapshot, if rewinding is enabled and it's time to take one.
+	fn take_rewind_snapshot(&mut self) {
+		if !self.game_loaded {
+			return;
+		}
+
+		let Some(rewind) = self.rewind.as_mut() else {
+			return;
+		};
+
+		if !rewind.frame_ran() {
+			return;
+		}
+
+		match self.save_state() {
+			Ok(state) => {
+				if let Some(rewind) = self.rewind.as_mut() {
+					rewind.push(state);
+				}
+			}
+			Err(err) => {
+				error!("Could not take rewind snapshot, disabling rewind: {err}");
+				self.rewind = None;
+			}
+		}
+	}
+
+	/// Enables rewinding. The core must support save states.
+	pub fn enable_rewind(&mut self, config: RewindConfig) {
+		self.rewind = Some(RewindBuffer::new(config));
+	}
+
+	/// Disables rewinding, throwing away all snapshots.
+	pub fn disable_rewind(&mut self) {
+		self.rewind = None;
+	}
+
+	pub fn rewind_enabled(&self) -> bool {
+		self.rewind.is_some()
+	}
+
+	/// Gets how many steps back can currently be rewound.
+	pub fn get_rewind_length(&self) -> usize {
+		self.rewind.as_ref().map_or(0, RewindBuffer::len)
+	}
+
+	/// Steps backwards to the previous rewind snapshot, and runs a frame from it so it's displayed.
+	/// Returns false if there was nothing left to rewind to. This works while paused, but not while
+	/// a movie is being recorded or replayed, since it would no longer line up with the game.
+	pub fn rewind_frame(&mut self) -> Result<bool> {
+		if self.movie_recorder.is_some() || self.movie_player.is_some() {
+			return Err(Error::MovieActive);
+		}
+
+		let rewind = self.rewind.as_mut().ok_or(Error::RewindNotEnabled)?;
+
+		let Some(state) = rewind.step_back() else {
+			return Ok(false);
+		};
+
+		self.load_state(&state)?;
+
+		// Audio played backwards is just noise, so don't play any.
+		let was_muted = self.audio.is_muted();
+		self.audio.set_muted(true);
+
+		self.rewinding = true;
+		self.run_core_frame();
+		self.rewinding = false;
+
+		self.audio.set_muted(was_muted);
+
+		// The frame was only run to show the snapshot, so go back to it.
+		self.load_state(&state)?;
+		Ok(true)
+	}
+
+	/// Starts recording input into a movie, beginning with a savestate of the current game.
+	/// The core must support save states.
+	pub fn start_recording(&mut self) -> Result<()> {
+		if self.movie_recorder.is_some() || self.movie_player.is_some() {
+			return Err(Error::MovieActive);
 		}
+
+		let initial_state = self.save_state()?;
+
+		let mut ports: Vec<(u32, u32)> = self
+			.input_devices
+			.iter()
+			.map(|(port, device)| (*port, device.device_type()))
+			.collect();
+		ports.sort_unstable();
+
+		self.movie_recorder = Some(MovieRecorder::new(Movie {
+			core_name: self.get_core_name()?,
+			game_name: self.get_game_name()?,
+			initial_state,
+			ports,
+			frames: Vec::new(),
+		}));
+
+		Ok(())
+	}
+
+	/// Stops recording, returning the recorded movie.
+	pub fn stop_recording(&mut self) -> Result<Movie> {
+		let recorder = self.movie_recorder.take().ok_or(Error::NotRecording)?;
+		info!("Recorded {} frames", recorder.frame_count());
+		Ok(recorder.finish())
+	}
+
+	pub fn is_recording(&self) -> bool {
+		self.movie_recorder.is_some()
+	}
+
+	/// Starts replaying a movie. The movie's initial state is loaded, and the core is given the
+	/// recorded input instead of input from plugged in devices until the movie ends or
+	/// [Frontend::stop_replay] is called.
+	pub fn start_replay(&mut self, movie: Movie) -> Result<()> {
+		if self.movie_recorder.is_some() || self.movie_player.is_some() {
+			return Err(Error::MovieActive);
+		}
+
+		let core_name = self.get_core_name()?;
+		if movie.core_name != core_name {
+			warn!(
+				"Movie was recorded with core {}, but {} is loaded. It may not replay correctly",
+				movie.core_name, core_name
+			);
+		}
+
+		self.load_state(&movie.initial_state)?;
+
+		self.movie_player = Some(MoviePlayer::new(movie));
+
+		Ok(())
+	}
+
+	/// Stops replaying, giving control back to the plugged in input devices.
+	pub fn stop_replay(&mut self) {
+		self.movie_player = None;
+	}
+
+	pub fn is_replaying(&self) -> bool {
+		self.movie_player.is_some()
+	}
+
+	/// Gets the number of frames replayed so far and the length of the movie being replayed.
+	pub fn get_replay_progress(&self) -> Option<(usize, usize)> {
+		self.movie_player.as_ref().map(MoviePlayer::progress)
+	}
+
+	/// Pauses emulation. While paused, [Frontend::run_frame] does nothing.
+	pub fn pause(&mut self) {
+		self.paused = true;
+		self.step_pending = false;
+	}
+
+	pub fn resume(&mut self) {
+		self.paused = false;
+		self.step_pending = false;
+	}
+
+	pub fn is_paused(&self) -> bool {
+		self.paused
+	}
+
+	/// Runs a single frame on the next call to [Frontend::run_frame] while paused.
+	pub fn step_frame(&mut self) {
+		if self.paused {
+			self.step_pending = true;
+		}
+	}
+
+	/// Sets how fast emulation should run. This is what cores are told, so the main loop
+	/// should pace itself with the same speed (see [crate::pacer::FramePacer::set_speed]).
+	/// Audio is muted while fast-forwarding.
+	pub fn set_speed(&mut self, speed: Speed) {
+		self.speed = speed;
+		self.audio.set_muted(matches!(speed, Speed::FastForward(_)));
+	}
+
+	pub fn get_speed(&self) -> Speed {
+		self.speed
+	}
+
+	/// Gets the throttle state cores are given. Rates are based on the given frame rate of the core.
+	pub(crate) fn get_throttle_state(&self, fps: f64) -> ThrottleState {
+		let (mode, rate) = if self.rewinding {
+			(RETRO_THROTTLE_REWINDING, 0.0)
+		} else if self.paused {
+			(RETRO_THROTTLE_FRAME_STEPPING, 0.0)
+		} else {
+			match self.speed {
+				Speed::Normal => (RETRO_THROTTLE_NONE, fps),
+				Speed::FastForward(multiplier) if multiplier.is_infinite() => {
+					(RETRO_THROTTLE_FAST_FORWARD, 0.0)
+				}
+				Speed::FastForward(multiplier) => (RETRO_THROTTLE_FAST_FORWARD, fps * multiplier),
+				Speed::SlowMotion(multiplier) => (RETRO_THROTTLE_SLOW_MOTION, fps * multiplier),
+			}
+		};
+
+		ThrottleState {
+			mode,
+			rate: rate as f32,
+		}
+	}
+
+	/// Sets how audio from the core is resampled and chunked before being given to
+	/// [FrontendInterface::audio_sample]. With [None], audio is given as the core produces it.
+	pub fn set_audio_config(&mut self, config: Option<AudioConfig>) -> Result<()> {
+		self.audio.set_config(config)?;
+
+		if let Some(av_info) = self.av_info.as_ref() {
+			self.audio.set_timing(&av_info.timing);
+		}
+
+		Ok(())
 	}
 }
 
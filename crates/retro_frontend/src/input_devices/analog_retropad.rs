@@ -0,0 +1,107 @@
+//! RetroPad with analog sticks
+use super::InputDevice;
+use crate::libretro_sys_new::{self, RETRO_DEVICE_INDEX_ANALOG_BUTTON};
+
+/// Implementation of the [InputDevice] trait for a RetroPad with two analog sticks,
+/// like a DualShock. Buttons are given to the core as [libretro_sys_new::DEVICE_JOYPAD],
+/// and the sticks and button pressure as [libretro_sys_new::DEVICE_ANALOG].
+pub struct AnalogRetroPad {
+	device_type: u32,
+	buttons: [i16; 16],
+
+	/// X and Y axes of the left and right sticks.
+	sticks: [[i16; 2]; 2],
+}
+
+impl AnalogRetroPad {
+	pub fn new() -> Self {
+		Self::with_device_type(libretro_sys_new::DEVICE_ANALOG)
+	}
+
+	/// Creates a pad which is plugged in as the given device type. This is useful for cores
+	/// which expect a subclass of [libretro_sys_new::DEVICE_ANALOG] for their analog controllers.
+	pub fn with_device_type(device_type: u32) -> Self {
+		Self {
+			device_type,
+			buttons: [0; 16],
+			sticks: [[0; 2]; 2],
+		}
+	}
+
+	/// Moves a stick. `index` is [libretro_sys_new::DEVICE_INDEX_ANALOG_LEFT] or
+	/// [libretro_sys_new::DEVICE_INDEX_ANALOG_RIGHT]; positive values are right and down.
+	pub fn set_stick(&mut self, index: u32, x: i16, y: i16) {
+		if let Some(stick) = self.sticks.get_mut(index as usize) {
+			*stick = [x, y];
+		}
+	}
+
+	/// Gets the position of a stick, as (x, y).
+	pub fn get_stick(&self, index: u32) -> (i16, i16) {
+		self.sticks
+			.get(index as usize)
+			.map_or((0, 0), |stick| (stick[0], stick[1]))
+	}
+}
+
+impl Default for AnalogRetroPad {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl InputDevice for AnalogRetroPad {
+	fn device_type(&self) -> u32 {
+		self.device_type
+	}
+
+	fn get_button(&self, id: u32) -> i16 {
+		self.buttons.get(id as usize).copied().unwrap_or(0)
+	}
+
+	fn reset(&mut self) {
+		self.buttons = [0; 16];
+		self.sticks = [[0; 2]; 2];
+	}
+
+	fn press_button(&mut self, id: u32, pressure: Option<i16>) {
+		if let Some(button) = self.buttons.get_mut(id as usize) {
+			*button = pressure.unwrap_or(0x7fff);
+		}
+	}
+
+	fn get_input(&self, device: u32, index: u32, id: u32) -> i16 {
+		match (device, index) {
+			(libretro_sys_new::DEVICE_JOYPAD, 0) => self.get_button(id),
+			(libretro_sys_new::DEVICE_ANALOG, RETRO_DEVICE_INDEX_ANALOG_BUTTON) => {
+				self.get_button(id)
+			}
+			(libretro_sys_new::DEVICE_ANALOG, _) => self
+				.sticks
+				.get(index as usize)
+				.and_then(|stick| stick.get(id as usize))
+				.copied()
+				.unwrap_or(0),
+			_ => 0,
+		}
+	}
+
+	fn set_input(&mut self, device: u32, index: u32, id: u32, value: i16) {
+		match (device, index) {
+			(libretro_sys_new::DEVICE_JOYPAD, 0) => self.press_button(id, Some(value)),
+			(libretro_sys_new::DEVICE_ANALOG, RETRO_DEVICE_INDEX_ANALOG_BUTTON) => {
+				self.press_button(id, Some(value))
+			}
+			(libretro_sys_new::DEVICE_ANALOG, _) => {
+				if let Some(axis) = self
+					.sticks
+					.get_mut(index as usize)
+					.and_then(|stick| stick.get_mut(id as usize))
+				{
+					*axis = value;
+				}
+			}
+			_ => {}
+		}
+	}
+}
@@ -2,9 +2,14 @@
 pub mod retropad;
 pub use retropad::*;
 
+pub mod analog_retropad;
+pub use analog_retropad::*;
+
 pub mod mouse;
 pub use mouse::*;
 
+use crate::libretro_sys_new::DEVICE_MASK;
+
 /// Trait for implementing Libretro input devices
 pub trait InputDevice {
 	/// Gets the device type. This should never EVER change, and simply return a constant.
@@ -19,4 +24,23 @@ pub trait InputDevice {
 
 	/// Presses a button/axis.
 	fn press_button(&mut self, id: u32, pressure: Option<i16>);
+
+	/// Gets the state of a button/axis as the core asks for it, where `device` is a base device
+	/// type (such as [DEVICE_ANALOG](crate::libretro_sys_new::DEVICE_ANALOG)) and `index` is only
+	/// used by some device types. By default, this is [InputDevice::get_button] for this device's
+	/// own base type and index 0, and 0 for anything else.
+	fn get_input(&self, device: u32, index: u32, id: u32) -> i16 {
+		if device == self.device_type() & DEVICE_MASK && index == 0 {
+			return self.get_button(id);
+		}
+
+		0
+	}
+
+	/// Sets the state of a button/axis as the core would ask for it with [InputDevice::get_input].
+	fn set_input(&mut self, device: u32, index: u32, id: u32, value: i16) {
+		if device == self.device_type() & DEVICE_MASK && index == 0 {
+			self.press_button(id, Some(value));
+		}
+	}
 }
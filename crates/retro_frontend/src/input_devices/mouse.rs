@@ -25,7 +25,7 @@ impl InputDevice for Mouse {
 	}
 
 	fn get_button(&self, id: u32) -> i16 {
-		if id > 8 {
+		if id >= 8 {
 			return 0;
 		}
 
@@ -39,7 +39,7 @@ impl InputDevice for Mouse {
 	}
 
 	fn press_button(&mut self, id: u32, pressure: Option<i16>) {
-		if id > 8 {
+		if id >= 8 {
 			return;
 		}
 
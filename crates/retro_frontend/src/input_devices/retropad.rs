@@ -21,7 +21,7 @@ impl InputDevice for RetroPad {
 	}
 
 	fn get_button(&self, id: u32) -> i16 {
-		if id > 16 {
+		if id >= 16 {
 			return 0;
 		}
 
@@ -35,7 +35,7 @@ impl InputDevice for RetroPad {
 	}
 
 	fn press_button(&mut self, id: u32, pressure: Option<i16>) {
-		if id > 16 {
+		if id >= 16 {
 			return;
 		}
 
@@ -1,12 +1,22 @@
 //! A libretro frontend as a reusable library crate.
 
+mod content;
 mod libretro_callbacks;
-mod libretro_core_variable;
 mod libretro_log;
 
+pub mod libretro_core_variable;
+pub mod libretro_input_info;
+pub mod libretro_subsystem;
 pub mod libretro_sys_new;
+pub mod libretro_vfs;
 
+pub mod audio;
+pub mod cheats;
 pub mod input_devices;
+pub mod movie;
+pub mod pacer;
+pub mod remote;
+pub mod rewind;
 pub mod util;
 
 pub mod frontend;
@@ -1,16 +1,90 @@
 //! Callbacks for libretro
-use crate::{frontend::*, libretro_log, util};
-use crate::{libretro_core_variable, libretro_sys_new::*};
+use crate::content::ContentInfoOverride;
+use crate::libretro_core_variable::{CoreVariable, CoreVariableCategory};
+use crate::libretro_input_info::{self, ControllerType};
+use crate::libretro_subsystem::Subsystem;
+use crate::movie::InputEvent;
+use crate::pacer::Speed;
+use crate::{frontend::*, libretro_log, libretro_sys_new::*, libretro_vfs, util};
 
 use rgb565::Rgb565;
 
+use std::collections::HashMap;
 use std::ffi;
 
 use tracing::{debug, error};
 
+/// Sets the frontend's core variables from a core options v1 definition array.
+unsafe fn set_core_options_v1(ptr: *const CoreOptionDefinition) {
+	if ptr.is_null() {
+		return;
+	}
+
+	let slice = util::terminated_array(ptr, |item| item.key.is_null());
+	let mut variables = HashMap::new();
+
+	for definition in slice {
+		match CoreVariable::from_v1_definition(definition) {
+			Ok((key, variable)) => {
+				variables.insert(key, variable);
+			}
+			Err(err) => {
+				error!("Core gave an invalid core option: {err}");
+			}
+		}
+	}
+
+	(*FRONTEND).variable_categories.clear();
+	(*FRONTEND).set_core_variables(variables);
+}
+
+/// Sets the frontend's core variables and categories from a core options v2 structure.
+unsafe fn set_core_options_v2(ptr: *const CoreOptionsV2) {
+	let options = match ptr.as_ref() {
+		Some(options) => options,
+		None => return,
+	};
+
+	let mut categories = HashMap::new();
+
+	if !options.categories.is_null() {
+		let slice = util::terminated_array(options.categories, |item| item.key.is_null());
+
+		for category in slice {
+			categories.insert(
+				util::string_from_c(category.key).unwrap(),
+				CoreVariableCategory {
+					description: util::string_from_c(category.desc).unwrap_or_default(),
+					info: util::string_from_c(category.info),
+				},
+			);
+		}
+	}
+
+	let mut variables = HashMap::new();
+
+	if !options.definitions.is_null() {
+		let slice = util::terminated_array(options.definitions, |item| item.key.is_null());
+
+		for definition in slice {
+			match CoreVariable::from_v2_definition(definition) {
+				Ok((key, variable)) => {
+					variables.insert(key, variable);
+				}
+				Err(err) => {
+					error!("Core gave an invalid core option: {err}");
+				}
+			}
+		}
+	}
+
+	(*FRONTEND).variable_categories = categories;
+	(*FRONTEND).set_core_variables(variables);
+}
+
 /// This function is used with HW OpenGL cores to transfer the current FBO's ID.
 unsafe extern "C" fn hw_gl_get_framebuffer() -> usize {
-	(*FRONTEND).gl_fbo_id as usize
+	(*FRONTEND).interface.hw_gl_framebuffer() as usize
 }
 
 pub(crate) unsafe extern "C" fn environment_callback(
@@ -30,21 +104,20 @@ pub(crate) unsafe extern "C" fn environment_callback(
 		}
 
 		ENVIRONMENT_SET_CONTROLLER_INFO => {
-			let ptr = data as *const ControllerInfo;
-
-			let slice = util::terminated_array(ptr, |item| {
-				return item.num_types == 0 && item.types.is_null();
+			let slice = util::terminated_array(data as *const ControllerInfo, |item| {
+				item.num_types == 0 && item.types.is_null()
 			});
 
-			for desc in slice {
-				debug!("{:?}", desc);
+			(*FRONTEND).controller_info = slice
+				.iter()
+				.map(|info| ControllerType::from_raw(info))
+				.collect();
 
-				for i in 0..desc.num_types as usize {
-					let p = desc.types.add(i).as_ref().unwrap();
+			for (port, types) in (*FRONTEND).controller_info.iter().enumerate() {
+				for controller in types {
 					debug!(
-						"type {i} = {:?} (name is {})",
-						p,
-						std::ffi::CStr::from_ptr(p.desc).to_str().unwrap()
+						"Port {port} supports {} (device {})",
+						controller.description, controller.device
 					);
 				}
 			}
@@ -53,18 +126,21 @@ pub(crate) unsafe extern "C" fn environment_callback(
 		}
 
 		ENVIRONMENT_SET_INPUT_DESCRIPTORS => {
-			let ptr = data as *const InputDescriptor;
-
-			let slice = util::terminated_array(ptr, |item| {
-				return item.description.is_null();
+			let slice = util::terminated_array(data as *const InputDescriptor, |item| {
+				item.description.is_null()
 			});
 
-			debug!("{} input descriptor entries", slice.len());
+			(*FRONTEND).input_descriptors = slice
+				.iter()
+				.map(|desc| libretro_input_info::InputDescriptor::from_raw(desc))
+				.collect();
 
-			for desc in slice {
-				debug!("Descriptor {:?}", desc);
-			}
+			debug!("{} input descriptor entries", slice.len());
+			return true;
+		}
 
+		ENVIRONMENT_SET_SUPPORT_NO_GAME => {
+			(*FRONTEND).supports_no_game = *(data as *const bool);
 			return true;
 		}
 
@@ -100,7 +176,60 @@ pub(crate) unsafe extern "C" fn environment_callback(
 			(*FRONTEND).fb_width = geometry.base_width;
 			(*FRONTEND).fb_height = geometry.base_height;
 
-			(*(*FRONTEND).interface).video_resize(geometry.base_width, geometry.base_height);
+			(*FRONTEND)
+				.interface
+				.video_resize(geometry.base_width, geometry.base_height);
+			return true;
+		}
+
+		ENVIRONMENT_SET_SYSTEM_AV_INFO => {
+			if data.is_null() {
+				return false;
+			}
+
+			let av_info = (data as *const SystemAvInfo).as_ref().unwrap();
+
+			// Audio can't be resampled without knowing its rate.
+			let sample_rate = av_info.timing.sample_rate;
+			if !sample_rate.is_finite() || sample_rate <= 0.0 {
+				error!("Core gave an invalid sample rate of {sample_rate}");
+				return false;
+			}
+
+			(*FRONTEND).audio.set_timing(&av_info.timing);
+			(*FRONTEND).fb_width = av_info.geometry.base_width;
+			(*FRONTEND).fb_height = av_info.geometry.base_height;
+			(*FRONTEND).av_info = Some(av_info.clone());
+
+			(*FRONTEND)
+				.interface
+				.video_resize(av_info.geometry.base_width, av_info.geometry.base_height);
+			return true;
+		}
+
+		RETRO_ENVIRONMENT_GET_FASTFORWARDING => {
+			*(data as *mut bool) = matches!((*FRONTEND).speed, Speed::FastForward(_));
+			return true;
+		}
+
+		RETRO_ENVIRONMENT_GET_THROTTLE_STATE => {
+			let fps = match (*FRONTEND).av_info.as_ref() {
+				Some(av_info) => av_info.timing.fps,
+				None => 0.0,
+			};
+
+			*(data as *mut ThrottleState) = (*FRONTEND).get_throttle_state(fps);
+			return true;
+		}
+
+		RETRO_ENVIRONMENT_GET_AUDIO_VIDEO_ENABLE => {
+			// Video is always wanted, but audio isn't while it's muted.
+			let mut enable = 1 << 0;
+			if !(*FRONTEND).audio.is_muted() {
+				enable |= 1 << 1;
+			}
+
+			*(data as *mut ffi::c_int) = enable;
 			return true;
 		}
 
@@ -120,7 +249,8 @@ pub(crate) unsafe extern "C" fn environment_callback(
 				return false;
 			}
 
-			let init_data = (*(*FRONTEND).interface).hw_gl_init();
+			let (width, height) = (*FRONTEND).get_size();
+			let init_data = (*FRONTEND).interface.hw_gl_init(width, height);
 
 			if init_data.is_none() {
 				return false;
@@ -175,9 +305,9 @@ pub(crate) unsafe extern "C" fn environment_callback(
 		}
 
 		ENVIRONMENT_GET_VARIABLE_UPDATE => {
-			// We currently pressent no changed variables to the core.
-			// TODO: this will change
-			*(data as *mut bool) = false;
+			// Tell the core if any variables have changed since it last asked.
+			*(data as *mut bool) = (*FRONTEND).variables_updated;
+			(*FRONTEND).variables_updated = false;
 			return true;
 		}
 
@@ -185,19 +315,156 @@ pub(crate) unsafe extern "C" fn environment_callback(
 			let ptr = data as *const Variable;
 			let slice = util::terminated_array(ptr, |item| item.key.is_null());
 
-			// populate variables hashmap
+			let mut variables = HashMap::new();
+
 			for var in slice {
-				let key = std::ffi::CStr::from_ptr(var.key).to_str().unwrap();
-				let value = std::ffi::CStr::from_ptr(var.value).to_str().unwrap();
+				let key = std::ffi::CStr::from_ptr(var.key).to_string_lossy();
+				let value = std::ffi::CStr::from_ptr(var.value).to_string_lossy();
+
+				// If a variable is broken, skip it; the core will use its own default
+				// when we tell it the variable doesn't exist.
+				match CoreVariable::parse(&value) {
+					Ok(parsed) => {
+						variables.insert(key.to_string(), parsed);
+					}
+					Err(err) => {
+						error!("Core gave an invalid variable {key}: {err}");
+					}
+				}
+			}
 
-				let parsed = libretro_core_variable::CoreVariable::parse(value);
+			(*FRONTEND).set_core_variables(variables);
+			return true;
+		}
 
-				(*FRONTEND).variables.insert(key.to_string(), parsed);
+		RETRO_ENVIRONMENT_SET_CONTENT_INFO_OVERRIDE => {
+			// A NULL pointer is used to check whether the frontend supports this.
+			if data.is_null() {
+				return true;
 			}
 
-			// Load settings
-			(*FRONTEND).load_settings();
+			let slice = util::terminated_array(data as *const SystemContentInfoOverride, |item| {
+				item.extensions.is_null()
+			});
+
+			for content_override in slice {
+				let extensions = util::string_from_c(content_override.extensions).unwrap();
 
+				for ext in extensions.split('|').filter(|ext| !ext.is_empty()) {
+					(*FRONTEND).content_overrides.insert(
+						ext.to_lowercase(),
+						ContentInfoOverride {
+							need_fullpath: content_override.need_fullpath,
+							persistent_data: content_override.persistent_data,
+						},
+					);
+				}
+			}
+
+			return true;
+		}
+
+		RETRO_ENVIRONMENT_GET_GAME_INFO_EXT => {
+			if (*FRONTEND).game_info_ext.is_empty() {
+				return false;
+			}
+
+			*(data as *mut *const GameInfoExt) = (*FRONTEND).game_info_ext.as_ptr();
+			return true;
+		}
+
+		ENVIRONMENT_SET_SUBSYSTEM_INFO => {
+			let slice =
+				util::terminated_array(data as *const SubsystemInfo, |item| item.ident.is_null());
+
+			(*FRONTEND).subsystems = slice.iter().map(|info| Subsystem::from_raw(info)).collect();
+
+			for subsystem in &(*FRONTEND).subsystems {
+				debug!(
+					"Core supports subsystem {} ({})",
+					subsystem.description, subsystem.ident
+				);
+			}
+
+			return true;
+		}
+
+		ENVIRONMENT_SET_DISK_CONTROL_INTERFACE => {
+			let callback = (data as *const DiskControlCallback).as_ref().unwrap();
+			(*FRONTEND).disk_control = Some(callback.into());
+			return true;
+		}
+
+		RETRO_ENVIRONMENT_GET_DISK_CONTROL_INTERFACE_VERSION => {
+			*(data as *mut ffi::c_uint) = RETRO_DISK_CONTROL_INTERFACE_VERSION;
+			return true;
+		}
+
+		RETRO_ENVIRONMENT_SET_DISK_CONTROL_EXT_INTERFACE => {
+			let callback = (data as *const DiskControlExtCallback).as_ref().unwrap();
+			(*FRONTEND).disk_control = Some(*callback);
+			return true;
+		}
+
+		RETRO_ENVIRONMENT_GET_VFS_INTERFACE => match (data as *mut VfsInterfaceInfo).as_mut() {
+			Some(info) => return libretro_vfs::get_vfs_interface(info),
+			None => return false,
+		},
+
+		RETRO_ENVIRONMENT_GET_CORE_OPTIONS_VERSION => {
+			*(data as *mut ffi::c_uint) = 2;
+			return true;
+		}
+
+		RETRO_ENVIRONMENT_SET_CORE_OPTIONS => {
+			set_core_options_v1(data as *const CoreOptionDefinition);
+			return true;
+		}
+
+		RETRO_ENVIRONMENT_SET_CORE_OPTIONS_INTL => {
+			// We only present US English to users, so ignore any localized options.
+			let Some(intl) = (data as *const CoreOptionsIntl).as_ref() else {
+				return false;
+			};
+			set_core_options_v1(intl.us);
+			return true;
+		}
+
+		RETRO_ENVIRONMENT_SET_CORE_OPTIONS_V2 => {
+			set_core_options_v2(data as *const CoreOptionsV2);
+			return true;
+		}
+
+		RETRO_ENVIRONMENT_SET_CORE_OPTIONS_V2_INTL => {
+			let Some(intl) = (data as *const CoreOptionsV2Intl).as_ref() else {
+				return false;
+			};
+			set_core_options_v2(intl.us);
+			return true;
+		}
+
+		RETRO_ENVIRONMENT_SET_CORE_OPTIONS_DISPLAY => {
+			if data.is_null() {
+				return false;
+			}
+
+			let display = (data as *const CoreOptionDisplay).as_ref().unwrap();
+
+			if let Some(key) = util::string_from_c(display.key) {
+				if let Some(variable) = (*FRONTEND).variables.get_mut(&key) {
+					variable.visible = display.visible;
+				}
+			}
+
+			return true;
+		}
+
+		RETRO_ENVIRONMENT_SET_CORE_OPTIONS_UPDATE_DISPLAY_CALLBACK => {
+			(*FRONTEND).variables_update_display_callback = if data.is_null() {
+				None
+			} else {
+				(*(data as *const CoreOptionsUpdateDisplayCallback)).callback
+			};
 			return true;
 		}
 
@@ -223,7 +490,7 @@ pub(crate) unsafe extern "C" fn video_refresh_callback(
 	//info!("Video refresh called, {width}, {height}, {pitch}");
 
 	if (*FRONTEND).fb_width != width || (*FRONTEND).fb_height != height {
-		(*(*FRONTEND).interface).video_resize(width, height);
+		(*FRONTEND).interface.video_resize(width, height);
 	}
 
 	// bleh
@@ -231,7 +498,7 @@ pub(crate) unsafe extern "C" fn video_refresh_callback(
 	(*FRONTEND).fb_height = height;
 
 	if pixels == (-1i64 as *const ffi::c_void) {
-		(*(*FRONTEND).interface).video_update_gl();
+		(*FRONTEND).interface.video_update_gl(width, height);
 		return;
 	}
 
@@ -260,13 +527,14 @@ pub(crate) unsafe extern "C" fn video_refresh_callback(
 					let comp = rgb.to_rgb888_components();
 
 					// Finally save the pixel data in the result array as an XRGB8888 value
-					(*FRONTEND).converted_pixel_buffer[y * pitch as usize + x] =
+					(&mut (*FRONTEND).converted_pixel_buffer)[y * pitch as usize + x] =
 						((comp[2] as u32) << 16) | ((comp[1] as u32) << 8) | (comp[0] as u32);
 				}
 			}
 
-			(*(*FRONTEND).interface)
-				.video_update(&(*FRONTEND).converted_pixel_buffer[..], pitch as u32);
+			(*FRONTEND)
+				.interface
+				.video_update(&(&(*FRONTEND).converted_pixel_buffer)[..], pitch as u32);
 		}
 		_ => {
 			let pixel_data_slice = std::slice::from_raw_parts(
@@ -274,33 +542,60 @@ pub(crate) unsafe extern "C" fn video_refresh_callback(
 				(pitch * height as usize) as usize,
 			);
 
-			(*(*FRONTEND).interface).video_update(&pixel_data_slice, pitch as u32);
+			(*FRONTEND)
+				.interface
+				.video_update(&pixel_data_slice, pitch as u32);
 		}
 	}
 }
 
 pub(crate) unsafe extern "C" fn input_poll_callback() {
-	(*(*FRONTEND).interface).input_poll();
+	let frontend = &mut *FRONTEND;
+	frontend.interface.input_poll(&mut frontend.input_devices);
 }
 
 pub(crate) unsafe extern "C" fn input_state_callback(
 	port: ffi::c_uint,
 	device: ffi::c_uint,
-	_index: ffi::c_uint, // not used?
+	index: ffi::c_uint,
 	button_id: ffi::c_uint,
 ) -> ffi::c_short {
-	if (*FRONTEND).input_devices.contains_key(&port) {
-		let joypad = *(*FRONTEND)
+	let value = get_input_state(port, device, index, button_id);
+
+	if let Some(recorder) = (*FRONTEND).movie_recorder.as_mut() {
+		recorder.record(InputEvent {
+			port,
+			device,
+			index,
+			id: button_id,
+			value,
+		});
+	}
+
+	value
+}
+
+unsafe fn get_input_state(port: u32, device: u32, index: u32, button_id: u32) -> i16 {
+	let frontend = &*FRONTEND;
+
+	// While a movie is replaying, input comes from it instead.
+	let input_device = match frontend.movie_player.as_ref() {
+		Some(player) => player.get_device(port),
+		None => frontend
 			.input_devices
 			.get(&port)
-			.expect("How do we get here when contains_key() returns true but the key doen't exist");
+			.map(|device| device.as_ref()),
+	};
 
-		if device == (*joypad).device_type() {
-			return (*joypad).get_button(button_id);
-		}
+	// Cores should only ask for base device types, but make sure.
+	match input_device {
+		Some(input_device) => input_device.get_input(device & DEVICE_MASK, index, button_id),
+		None => 0,
 	}
+}
 
-	0
+pub(crate) unsafe extern "C" fn audio_sample_callback(left: i16, right: i16) {
+	(*FRONTEND).audio.push_frame(left, right);
 }
 
 pub(crate) unsafe extern "C" fn audio_sample_batch_callback(
@@ -310,8 +605,9 @@ pub(crate) unsafe extern "C" fn audio_sample_batch_callback(
 ) -> usize {
 	let slice = std::slice::from_raw_parts(samples, frames * 2);
 
-	// I might not need to give the callback the amount of frames since it can figure it out as
-	// slice.len() / 2, but /shrug
-	(*(*FRONTEND).interface).audio_sample(slice, frames);
+	let frontend = &mut *FRONTEND;
+	frontend
+		.audio
+		.push_samples(slice, frontend.interface.as_mut());
 	frames
 }
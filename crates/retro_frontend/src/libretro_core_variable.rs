@@ -1,69 +1,190 @@
 //! Helpers for dealing with Libretro configuration values.
 
+use crate::libretro_sys_new::*;
+use crate::result::{Error, Result};
+use crate::util;
 use serde::{Deserialize, Serialize};
 use std::ffi::CString;
 
+/// A category core variables can be grouped under. (Core options v2)
+#[derive(Clone, Debug)]
+pub struct CoreVariableCategory {
+	/// Description of category
+	pub description: String,
+
+	/// Additional information about the category, if the core provided it.
+	pub info: Option<String>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CoreVariable {
 	/// Description of variable
 	pub description: String,
 
+	/// Additional information about the variable, if the core provided it.
+	#[serde(default)]
+	pub info: Option<String>,
+
+	/// Key of the category this variable belongs to, if any.
+	#[serde(default)]
+	pub category: Option<String>,
+
 	/// possible choices
 	pub choices: Vec<String>,
 
-	/// Value. May not be pressent; if so, assume choices[0]
+	/// Human-readable labels for each choice, in the same order as [CoreVariable::choices].
+	#[serde(default)]
+	pub labels: Vec<String>,
+
+	/// Default value. May not be pressent; if so, assume choices[0]
+	#[serde(default)]
+	pub default_value: Option<String>,
+
+	/// Value. May not be pressent; if so, assume the default value
 	pub value: Option<String>,
 
+	/// If the core wants this variable to be shown to users.
+	#[serde(skip, default = "default_visible")]
+	pub visible: bool,
+
 	/// C value. Passed/cached to libretro.
 	#[serde(skip)]
 	c_value: Option<CString>,
 }
 
+fn default_visible() -> bool {
+	true
+}
+
+/// Converts a core option values array into (choices, labels).
+unsafe fn parse_option_values(values: &[CoreOptionValue]) -> (Vec<String>, Vec<String>) {
+	let mut choices = Vec::new();
+	let mut labels = Vec::new();
+
+	for value in values.iter().take_while(|value| !value.value.is_null()) {
+		let choice = util::string_from_c(value.value).unwrap();
+		let label = util::string_from_c(value.label).unwrap_or_else(|| choice.clone());
+
+		choices.push(choice);
+		labels.push(label);
+	}
+
+	(choices, labels)
+}
+
 impl CoreVariable {
-	/// Parses this core variable.
-	pub fn parse(str: &str) -> Self {
-		let string = str.to_string();
-
-		match string.find(';') {
-			Some(index) => {
-				let name = &string[0..index];
-
-				// FIXME: Instead of panicing, we should return a Result or the like
-				if string.chars().nth(index + 1).unwrap() != ' ' {
-					panic!("Improperly formatted core variable");
-				}
-
-				let raw_choices = string[index + 2..].to_string();
-				let choices = raw_choices.split('|').map(|s| s.to_string()).collect();
-
-				Self {
-					description: name.to_string(),
-					choices: choices,
-					value: None,
-					c_value: None,
-				}
-			}
-			None => panic!("??? Couldn't find"),
+	/// Parses a core variable definition, in the form of `Description; choice1|choice2|...`.
+	pub fn parse(str: &str) -> Result<Self> {
+		let (name, raw_choices) = match str.split_once(';') {
+			Some(pair) => pair,
+			None => return Err(Error::InvalidCoreVariable(str.to_string())),
+		};
+
+		// The libretro API says there should be a single space after the ';',
+		// but some cores don't bother, so be lenient about it.
+		let choices: Vec<String> = raw_choices
+			.trim_start()
+			.split('|')
+			.filter(|s| !s.is_empty())
+			.map(|s| s.to_string())
+			.collect();
+
+		if choices.is_empty() {
+			return Err(Error::InvalidCoreVariable(str.to_string()));
+		}
+
+		Ok(Self {
+			description: name.to_string(),
+			info: None,
+			category: None,
+			labels: choices.clone(),
+			choices,
+			default_value: None,
+			value: None,
+			visible: true,
+			c_value: None,
+		})
+	}
+
+	/// Creates a core variable from a core options v1 definition, returning its key and the variable.
+	///
+	/// # Safety
+	/// The definition must contain valid pointers, as the libretro API requires.
+	pub unsafe fn from_v1_definition(definition: &CoreOptionDefinition) -> Result<(String, Self)> {
+		let key = util::string_from_c(definition.key).unwrap_or_default();
+		let (choices, labels) = parse_option_values(&definition.values[..]);
+
+		if choices.is_empty() {
+			return Err(Error::InvalidCoreVariable(key));
+		}
+
+		Ok((
+			key,
+			Self {
+				description: util::string_from_c(definition.desc).unwrap_or_default(),
+				info: util::string_from_c(definition.info),
+				category: None,
+				choices,
+				labels,
+				default_value: util::string_from_c(definition.default_value),
+				value: None,
+				visible: true,
+				c_value: None,
+			},
+		))
+	}
+
+	/// Creates a core variable from a core options v2 definition, returning its key and the variable.
+	///
+	/// # Safety
+	/// The definition must contain valid pointers, as the libretro API requires.
+	pub unsafe fn from_v2_definition(definition: &CoreOptionV2Definition) -> Result<(String, Self)> {
+		let key = util::string_from_c(definition.key).unwrap_or_default();
+		let (choices, labels) = parse_option_values(&definition.values[..]);
+
+		if choices.is_empty() {
+			return Err(Error::InvalidCoreVariable(key));
+		}
+
+		Ok((
+			key,
+			Self {
+				description: util::string_from_c(definition.desc).unwrap_or_default(),
+				info: util::string_from_c(definition.info),
+				category: util::string_from_c(definition.category_key),
+				choices,
+				labels,
+				default_value: util::string_from_c(definition.default_value),
+				value: None,
+				visible: true,
+				c_value: None,
+			},
+		))
+	}
+
+	/// Gets the value of this variable that will be given to the core, as a Rust string.
+	pub fn current_value(&self) -> &str {
+		if let Some(value) = self.value.as_ref() {
+			return value;
+		}
+
+		match self.default_value.as_ref() {
+			Some(default_value) if self.choices.contains(default_value) => default_value,
+			_ => &self.choices[0],
 		}
 	}
 
 	/// Gets this variable's value
 	pub fn get_value(&mut self) -> &CString {
-		let rust_value = if self.value.is_some() {
-			self.value.as_ref().unwrap()
-		} else {
-			&self.choices[0]
-		};
-
 		if self.c_value.is_none() {
-			self.c_value = Some(CString::new(rust_value.as_bytes()).expect("aaa"));
+			self.c_value = Some(CString::new(self.current_value().as_bytes()).expect("aaa"));
 		}
 		self.c_value.as_ref().unwrap()
 	}
 
 	/// Sets a new value
-	pub fn set_value(&mut self, value: &String) {
-		self.value = Some(value.clone());
+	pub fn set_value(&mut self, value: &str) {
+		self.value = Some(value.to_string());
 		self.c_value = None;
 	}
 }
@@ -0,0 +1,75 @@
+//! Owned versions of the input information cores publish with [ENVIRONMENT_SET_INPUT_DESCRIPTORS]
+//! and [ENVIRONMENT_SET_CONTROLLER_INFO].
+
+use crate::libretro_sys_new::*;
+use crate::util;
+
+/// Describes what a button or axis does in the loaded game, e.g. "Jump" for RetroPad B on port 0.
+#[derive(Clone, Debug)]
+pub struct InputDescriptor {
+	pub port: u32,
+
+	/// The base device type, e.g. [DEVICE_JOYPAD].
+	pub device: u32,
+
+	/// The index the core asks for, which is only used by analog devices.
+	pub index: u32,
+
+	pub id: u32,
+
+	/// Human-readable description, e.g. "Jump".
+	pub description: String,
+}
+
+impl InputDescriptor {
+	/// Creates an owned input descriptor from one a core gave.
+	///
+	/// # Safety
+	/// The pointers in `desc` must be valid, as the libretro API requires.
+	pub(crate) unsafe fn from_raw(desc: &libretro_sys::InputDescriptor) -> Self {
+		Self {
+			port: desc.port,
+			device: desc.device,
+			index: desc.index,
+			id: desc.id,
+			description: util::string_from_c(desc.description).unwrap_or_default(),
+		}
+	}
+}
+
+/// A type of controller a core supports on a port.
+#[derive(Clone, Debug)]
+pub struct ControllerType {
+	/// Human-readable name of the controller, e.g. "Super Scope".
+	pub description: String,
+
+	/// Device type to plug in for this controller. This can be a subclass of a base
+	/// device type; see [ControllerType::base_device].
+	pub device: u32,
+}
+
+impl ControllerType {
+	/// Gets the base device type this controller is a subclass of, which
+	/// is the type the core will ask for input with.
+	pub fn base_device(&self) -> u32 {
+		self.device & DEVICE_MASK
+	}
+
+	/// Creates the controller types supported on one port, from what the core gave.
+	///
+	/// # Safety
+	/// The pointers in `info` must be valid, as the libretro API requires.
+	pub(crate) unsafe fn from_raw(info: &ControllerInfo) -> Vec<Self> {
+		if info.types.is_null() {
+			return Vec::new();
+		}
+
+		std::slice::from_raw_parts(info.types, info.num_types as usize)
+			.iter()
+			.map(|controller| Self {
+				description: util::string_from_c(controller.desc).unwrap_or_default(),
+				device: controller.id,
+			})
+			.collect()
+	}
+}
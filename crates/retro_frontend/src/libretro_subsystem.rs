@@ -0,0 +1,90 @@
+//! Owned versions of the subsystem information cores publish with [ENVIRONMENT_SET_SUBSYSTEM_INFO].
+
+use crate::libretro_sys_new::*;
+use crate::util;
+
+/// Converts a pointer and count from the core into a slice.
+unsafe fn slice_from_raw<'a, T>(ptr: *const T, len: u32) -> &'a [T] {
+	if ptr.is_null() || len == 0 {
+		return &[];
+	}
+
+	std::slice::from_raw_parts(ptr, len as usize)
+}
+
+/// A persistent memory type associated with a piece of subsystem content.
+#[derive(Clone, Debug)]
+pub struct SubsystemMemory {
+	/// Extension associated with the memory type, e.g. "psram".
+	pub extension: String,
+
+	/// Memory type to give to `retro_get_memory_data()`.
+	pub kind: u32,
+}
+
+/// Describes one piece of content a subsystem loads.
+#[derive(Clone, Debug)]
+pub struct SubsystemRom {
+	/// What the content is (e.g. "SGB BIOS" or "GB ROM").
+	pub description: String,
+
+	/// Lowercase extensions this content can have. If empty, the core didn't say.
+	pub valid_extensions: Vec<String>,
+
+	pub need_fullpath: bool,
+	pub block_extract: bool,
+
+	/// If false, this content can be left out.
+	pub required: bool,
+
+	pub memory: Vec<SubsystemMemory>,
+}
+
+/// A subsystem (a special way of loading games) supported by a core.
+#[derive(Clone, Debug)]
+pub struct Subsystem {
+	/// Human-readable name, e.g. "Super Game Boy".
+	pub description: String,
+
+	/// Short identifier, e.g. "sgb".
+	pub ident: String,
+
+	/// The type given to `retro_load_game_special()`.
+	pub id: u32,
+
+	/// The content this subsystem loads. The first is the most significant (e.g. the Game Boy ROM for Super Game Boy).
+	pub roms: Vec<SubsystemRom>,
+}
+
+impl Subsystem {
+	/// Creates an owned subsystem from the information a core gave.
+	///
+	/// # Safety
+	/// The pointers in `info` must be valid, as the libretro API requires.
+	pub(crate) unsafe fn from_raw(info: &SubsystemInfo) -> Self {
+		let roms = slice_from_raw(info.roms, info.num_roms)
+			.iter()
+			.map(|rom| SubsystemRom {
+				description: util::string_from_c(rom.desc).unwrap_or_default(),
+				valid_extensions: util::extensions_from_c(rom.valid_extensions),
+				need_fullpath: rom.need_fullpath,
+				block_extract: rom.block_extract,
+				required: rom.required,
+				memory: slice_from_raw(rom.memory, rom.num_memory)
+					.iter()
+					.map(|memory| SubsystemMemory {
+						extension: util::string_from_c(memory.extension).unwrap_or_default(),
+						kind: memory.kind,
+					})
+					.collect(),
+			})
+			.collect();
+
+		Self {
+			description: util::string_from_c(info.desc).unwrap_or_default(),
+			ident: util::string_from_c(info.ident).unwrap_or_default(),
+			id: info.id,
+			roms,
+		}
+	}
+}
@@ -36,3 +36,303 @@ pub const RETRO_ENVIRONMENT_SET_CONTENT_INFO_OVERRIDE: ffi::c_uint = 65;
 
 /// *const *const [GameInfoExt]
 pub const RETRO_ENVIRONMENT_GET_GAME_INFO_EXT: ffi::c_uint = 66;
+
+/// Index for [DEVICE_ANALOG] which gives the analog value (pressure) of RetroPad buttons.
+/// The id is the RetroPad button id.
+pub const RETRO_DEVICE_INDEX_ANALOG_BUTTON: ffi::c_uint = 2;
+
+/// Maximum number of values a core option can have.
+pub const RETRO_NUM_CORE_OPTION_VALUES_MAX: usize = 128;
+
+#[repr(C)]
+pub struct CoreOptionValue {
+	/// Expected option value
+	pub value: *const ffi::c_char,
+
+	/// Human-readable value label. If NULL, value itself will be displayed.
+	pub label: *const ffi::c_char,
+}
+
+#[repr(C)]
+pub struct CoreOptionDefinition {
+	pub key: *const ffi::c_char,
+	pub desc: *const ffi::c_char,
+	pub info: *const ffi::c_char,
+
+	/// Array of [CoreOptionValue] structs, terminated by NULL
+	pub values: [CoreOptionValue; RETRO_NUM_CORE_OPTION_VALUES_MAX],
+
+	/// Default core option value. Must match one of the values in the values array.
+	pub default_value: *const ffi::c_char,
+}
+
+#[repr(C)]
+pub struct CoreOptionsIntl {
+	/// Pointer to an array of [CoreOptionDefinition] structs (US English)
+	pub us: *const CoreOptionDefinition,
+
+	/// Pointer to an array of [CoreOptionDefinition] structs (current frontend language)
+	pub local: *const CoreOptionDefinition,
+}
+
+#[repr(C)]
+pub struct CoreOptionV2Category {
+	pub key: *const ffi::c_char,
+	pub desc: *const ffi::c_char,
+	pub info: *const ffi::c_char,
+}
+
+#[repr(C)]
+pub struct CoreOptionV2Definition {
+	pub key: *const ffi::c_char,
+	pub desc: *const ffi::c_char,
+	pub desc_categorized: *const ffi::c_char,
+	pub info: *const ffi::c_char,
+	pub info_categorized: *const ffi::c_char,
+	pub category_key: *const ffi::c_char,
+
+	/// Array of [CoreOptionValue] structs, terminated by NULL
+	pub values: [CoreOptionValue; RETRO_NUM_CORE_OPTION_VALUES_MAX],
+
+	/// Default core option value. Must match one of the values in the values array.
+	pub default_value: *const ffi::c_char,
+}
+
+#[repr(C)]
+pub struct CoreOptionsV2 {
+	/// Array of [CoreOptionV2Category] structs (NULL key terminates it). May be NULL.
+	pub categories: *const CoreOptionV2Category,
+
+	/// Array of [CoreOptionV2Definition] structs (NULL key terminates it)
+	pub definitions: *const CoreOptionV2Definition,
+}
+
+#[repr(C)]
+pub struct CoreOptionsV2Intl {
+	pub us: *const CoreOptionsV2,
+	pub local: *const CoreOptionsV2,
+}
+
+#[repr(C)]
+pub struct CoreOptionDisplay {
+	pub key: *const ffi::c_char,
+	pub visible: bool,
+}
+
+pub type CoreOptionsUpdateDisplayCallbackFn = unsafe extern "C" fn() -> bool;
+
+#[repr(C)]
+pub struct CoreOptionsUpdateDisplayCallback {
+	pub callback: Option<CoreOptionsUpdateDisplayCallbackFn>,
+}
+
+/// *mut ffi::c_uint
+pub const RETRO_ENVIRONMENT_GET_CORE_OPTIONS_VERSION: ffi::c_uint = 52;
+
+/// *const [CoreOptionDefinition] (array, NULL key terminates it)
+pub const RETRO_ENVIRONMENT_SET_CORE_OPTIONS: ffi::c_uint = 53;
+
+/// *const [CoreOptionsIntl]
+pub const RETRO_ENVIRONMENT_SET_CORE_OPTIONS_INTL: ffi::c_uint = 54;
+
+/// *const [CoreOptionDisplay]
+pub const RETRO_ENVIRONMENT_SET_CORE_OPTIONS_DISPLAY: ffi::c_uint = 55;
+
+/// *const [CoreOptionsV2]
+pub const RETRO_ENVIRONMENT_SET_CORE_OPTIONS_V2: ffi::c_uint = 67;
+
+/// *const [CoreOptionsV2Intl]
+pub const RETRO_ENVIRONMENT_SET_CORE_OPTIONS_V2_INTL: ffi::c_uint = 68;
+
+/// *const [CoreOptionsUpdateDisplayCallback]
+pub const RETRO_ENVIRONMENT_SET_CORE_OPTIONS_UPDATE_DISPLAY_CALLBACK: ffi::c_uint = 69;
+
+/// Read access
+pub const RETRO_VFS_FILE_ACCESS_READ: ffi::c_uint = 1 << 0;
+
+/// Write access. Will discard existing contents unless combined with [RETRO_VFS_FILE_ACCESS_UPDATE_EXISTING]
+pub const RETRO_VFS_FILE_ACCESS_WRITE: ffi::c_uint = 1 << 1;
+
+/// Read/write access
+pub const RETRO_VFS_FILE_ACCESS_READ_WRITE: ffi::c_uint =
+	RETRO_VFS_FILE_ACCESS_READ | RETRO_VFS_FILE_ACCESS_WRITE;
+
+/// Prevents discarding existing file contents when opening for write
+pub const RETRO_VFS_FILE_ACCESS_UPDATE_EXISTING: ffi::c_uint = 1 << 2;
+
+pub const RETRO_VFS_SEEK_POSITION_START: ffi::c_int = 0;
+pub const RETRO_VFS_SEEK_POSITION_CURRENT: ffi::c_int = 1;
+pub const RETRO_VFS_SEEK_POSITION_END: ffi::c_int = 2;
+
+pub const RETRO_VFS_STAT_IS_VALID: ffi::c_int = 1 << 0;
+pub const RETRO_VFS_STAT_IS_DIRECTORY: ffi::c_int = 1 << 1;
+pub const RETRO_VFS_STAT_IS_CHARACTER_SPECIAL: ffi::c_int = 1 << 2;
+
+// File/directory handles are opaque to the core, so we just use void pointers for them here.
+
+pub type VfsGetPathFn = unsafe extern "C" fn(stream: *mut ffi::c_void) -> *const ffi::c_char;
+pub type VfsOpenFn = unsafe extern "C" fn(
+	path: *const ffi::c_char,
+	mode: ffi::c_uint,
+	hints: ffi::c_uint,
+) -> *mut ffi::c_void;
+pub type VfsCloseFn = unsafe extern "C" fn(stream: *mut ffi::c_void) -> ffi::c_int;
+pub type VfsSizeFn = unsafe extern "C" fn(stream: *mut ffi::c_void) -> i64;
+pub type VfsTruncateFn = unsafe extern "C" fn(stream: *mut ffi::c_void, length: i64) -> i64;
+pub type VfsTellFn = unsafe extern "C" fn(stream: *mut ffi::c_void) -> i64;
+pub type VfsSeekFn =
+	unsafe extern "C" fn(stream: *mut ffi::c_void, offset: i64, seek_position: ffi::c_int) -> i64;
+pub type VfsReadFn =
+	unsafe extern "C" fn(stream: *mut ffi::c_void, s: *mut ffi::c_void, len: u64) -> i64;
+pub type VfsWriteFn =
+	unsafe extern "C" fn(stream: *mut ffi::c_void, s: *const ffi::c_void, len: u64) -> i64;
+pub type VfsFlushFn = unsafe extern "C" fn(stream: *mut ffi::c_void) -> ffi::c_int;
+pub type VfsRemoveFn = unsafe extern "C" fn(path: *const ffi::c_char) -> ffi::c_int;
+pub type VfsRenameFn =
+	unsafe extern "C" fn(old_path: *const ffi::c_char, new_path: *const ffi::c_char) -> ffi::c_int;
+pub type VfsStatFn = unsafe extern "C" fn(path: *const ffi::c_char, size: *mut i32) -> ffi::c_int;
+pub type VfsMkdirFn = unsafe extern "C" fn(dir: *const ffi::c_char) -> ffi::c_int;
+pub type VfsOpendirFn =
+	unsafe extern "C" fn(dir: *const ffi::c_char, include_hidden: bool) -> *mut ffi::c_void;
+pub type VfsReaddirFn = unsafe extern "C" fn(dirstream: *mut ffi::c_void) -> bool;
+pub type VfsDirentGetNameFn =
+	unsafe extern "C" fn(dirstream: *mut ffi::c_void) -> *const ffi::c_char;
+pub type VfsDirentIsDirFn = unsafe extern "C" fn(dirstream: *mut ffi::c_void) -> bool;
+pub type VfsClosedirFn = unsafe extern "C" fn(dirstream: *mut ffi::c_void) -> ffi::c_int;
+
+#[repr(C)]
+pub struct VfsInterface {
+	// VFS API v1
+	pub get_path: VfsGetPathFn,
+	pub open: VfsOpenFn,
+	pub close: VfsCloseFn,
+	pub size: VfsSizeFn,
+	pub tell: VfsTellFn,
+	pub seek: VfsSeekFn,
+	pub read: VfsReadFn,
+	pub write: VfsWriteFn,
+	pub flush: VfsFlushFn,
+	pub remove: VfsRemoveFn,
+	pub rename: VfsRenameFn,
+
+	// VFS API v2
+	pub truncate: VfsTruncateFn,
+
+	// VFS API v3
+	pub stat: VfsStatFn,
+	pub mkdir: VfsMkdirFn,
+	pub opendir: VfsOpendirFn,
+	pub readdir: VfsReaddirFn,
+	pub dirent_get_name: VfsDirentGetNameFn,
+	pub dirent_is_dir: VfsDirentIsDirFn,
+	pub closedir: VfsClosedirFn,
+}
+
+#[repr(C)]
+pub struct VfsInterfaceInfo {
+	/// Set by core: the minimum VFS API version the core needs.
+	/// Set by frontend: the VFS API version the frontend supports.
+	pub required_interface_version: u32,
+
+	/// Frontend writes the interface pointer here.
+	pub iface: *const VfsInterface,
+}
+
+/// The VFS API version we implement.
+pub const RETRO_VFS_INTERFACE_VERSION: u32 = 3;
+
+/// *mut [VfsInterfaceInfo]
+pub const RETRO_ENVIRONMENT_GET_VFS_INTERFACE: ffi::c_uint = 45 | ENVIRONMENT_EXPERIMENTAL;
+
+/// Sets the initial disk image the core should boot from. Called before `retro_load_game`.
+pub type SetInitialImageFn =
+	unsafe extern "C" fn(index: ffi::c_uint, path: *const ffi::c_char) -> bool;
+
+/// Gets the path of the disk image at the given index, writing it to the given buffer.
+pub type GetImagePathFn =
+	unsafe extern "C" fn(index: ffi::c_uint, path: *mut ffi::c_char, len: usize) -> bool;
+
+/// Gets a label for the disk image at the given index, writing it to the given buffer.
+pub type GetImageLabelFn =
+	unsafe extern "C" fn(index: ffi::c_uint, label: *mut ffi::c_char, len: usize) -> bool;
+
+/// Extended version of [DiskControlCallback]. The functions added over it are optional.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct DiskControlExtCallback {
+	pub set_eject_state: SetEjectStateFn,
+	pub get_eject_state: GetEjectStateFn,
+
+	pub get_image_index: GetImageIndexFn,
+	pub set_image_index: SetImageIndexFn,
+	pub get_num_images: GetNumImagesFn,
+
+	pub replace_image_index: ReplaceImageIndexFn,
+	pub add_image_index: AddImageIndexFn,
+
+	pub set_initial_image: Option<SetInitialImageFn>,
+	pub get_image_path: Option<GetImagePathFn>,
+	pub get_image_label: Option<GetImageLabelFn>,
+}
+
+impl From<&DiskControlCallback> for DiskControlExtCallback {
+	fn from(callback: &DiskControlCallback) -> Self {
+		Self {
+			set_eject_state: callback.set_eject_state,
+			get_eject_state: callback.get_eject_state,
+			get_image_index: callback.get_image_index,
+			set_image_index: callback.set_image_index,
+			get_num_images: callback.get_num_images,
+			replace_image_index: callback.replace_image_index,
+			add_image_index: callback.add_image_index,
+			set_initial_image: None,
+			get_image_path: None,
+			get_image_label: None,
+		}
+	}
+}
+
+/// The disk control interface version we implement.
+pub const RETRO_DISK_CONTROL_INTERFACE_VERSION: ffi::c_uint = 1;
+
+/// *mut c_uint
+pub const RETRO_ENVIRONMENT_GET_DISK_CONTROL_INTERFACE_VERSION: ffi::c_uint = 57;
+
+/// *const [DiskControlExtCallback]
+pub const RETRO_ENVIRONMENT_SET_DISK_CONTROL_EXT_INTERFACE: ffi::c_uint = 58;
+
+/// *mut bool
+pub const RETRO_ENVIRONMENT_GET_FASTFORWARDING: ffi::c_uint = 49 | ENVIRONMENT_EXPERIMENTAL;
+
+/// *mut ffi::c_int; bit 0 is set if video should be rendered, bit 1 if audio should be.
+pub const RETRO_ENVIRONMENT_GET_AUDIO_VIDEO_ENABLE: ffi::c_uint = 47 | ENVIRONMENT_EXPERIMENTAL;
+
+/// *mut [ThrottleState]
+pub const RETRO_ENVIRONMENT_GET_THROTTLE_STATE: ffi::c_uint = 71 | ENVIRONMENT_EXPERIMENTAL;
+
+/// During normal operation. Rate is the core's FPS.
+pub const RETRO_THROTTLE_NONE: ffi::c_uint = 0;
+
+/// While paused or stepping single frames. Rate is 0.
+pub const RETRO_THROTTLE_FRAME_STEPPING: ffi::c_uint = 1;
+
+/// While fast-forwarding. Rate is 0 if not limited to a maximum speed.
+pub const RETRO_THROTTLE_FAST_FORWARD: ffi::c_uint = 2;
+
+/// During slow motion. Rate is less than the core's FPS.
+pub const RETRO_THROTTLE_SLOW_MOTION: ffi::c_uint = 3;
+
+/// While rewinding. Rate is 0 if the frontend isn't aiming for a specific rate.
+pub const RETRO_THROTTLE_REWINDING: ffi::c_uint = 4;
+
+/// While the frontend doesn't throttle in any way. Rate is 0.
+pub const RETRO_THROTTLE_UNBLOCKED: ffi::c_uint = 6;
+
+#[repr(C)]
+pub struct ThrottleState {
+	/// One of the RETRO_THROTTLE_* constants.
+	pub mode: ffi::c_uint,
+
+	/// Average frame rate the frontend is aiming for.
+	pub rate: f32,
+}
@@ -1,14 +1,604 @@
 //! Libretro VFS
+//!
+//! Cores which support the libretro VFS interface do all of their file access through the frontend.
+//! We forward that access to a [VfsBackend], which lets users of the frontend decide what a core
+//! is allowed to touch (or serve files from somewhere other than the host filesystem entirely).
 
+use crate::frontend::FRONTEND;
 use crate::libretro_sys_new::*;
-use std::path;
+use std::ffi::{self, CString};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Component, Path, PathBuf};
 
+use tracing::error;
+
+/// How a file should be opened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OpenMode {
+	/// The file will be read from.
+	pub read: bool,
+
+	/// The file will be written to. Existing contents are discarded
+	/// unless [OpenMode::update_existing] is set.
+	pub write: bool,
+
+	/// Keep existing contents when opening for writing.
+	pub update_existing: bool,
+}
+
+/// Information about a file or directory.
+#[derive(Clone, Copy, Debug)]
+pub struct FileStat {
+	pub is_directory: bool,
+	pub is_character_special: bool,
+	pub size: u64,
+}
+
+/// An entry in a directory.
+#[derive(Clone, Debug)]
+pub struct DirEntry {
+	pub name: String,
+	pub is_directory: bool,
+}
+
+/// A file opened through a [VfsBackend].
+pub trait VfsFile: Read + Write + Seek {
+	/// Gets the size of this file.
+	fn size(&mut self) -> io::Result<u64>;
+
+	/// Truncates (or extends) this file to the given length.
+	fn truncate(&mut self, length: u64) -> io::Result<()>;
+}
+
+impl VfsFile for fs::File {
+	fn size(&mut self) -> io::Result<u64> {
+		Ok(self.metadata()?.len())
+	}
+
+	fn truncate(&mut self, length: u64) -> io::Result<()> {
+		self.set_len(length)
+	}
+}
+
+/// Trait for implementing a filesystem that cores can access through the libretro VFS interface.
+pub trait VfsBackend {
+	/// Opens a file.
+	fn open(&mut self, path: &Path, mode: OpenMode) -> io::Result<Box<dyn VfsFile>>;
+
+	/// Removes a file.
+	fn remove(&mut self, path: &Path) -> io::Result<()>;
+
+	/// Renames a file.
+	fn rename(&mut self, old_path: &Path, new_path: &Path) -> io::Result<()>;
+
+	/// Gets information about a file or directory.
+	fn stat(&mut self, path: &Path) -> io::Result<FileStat>;
+
+	/// Creates a directory.
+	fn mkdir(&mut self, path: &Path) -> io::Result<()>;
+
+	/// Lists the entries of a directory.
+	fn read_dir(&mut self, path: &Path, include_hidden: bool) -> io::Result<Vec<DirEntry>>;
+}
+
+/// A [VfsBackend] which gives cores unrestricted access to the host filesystem.
+/// This is what cores not using the VFS interface get anyways.
+pub struct HostVfs;
+
+impl VfsBackend for HostVfs {
+	fn open(&mut self, path: &Path, mode: OpenMode) -> io::Result<Box<dyn VfsFile>> {
+		let file = fs::OpenOptions::new()
+			.read(mode.read)
+			.write(mode.write)
+			.create(mode.write)
+			.truncate(mode.write && !mode.update_existing)
+			.open(path)?;
+
+		Ok(Box::new(file))
+	}
+
+	fn remove(&mut self, path: &Path) -> io::Result<()> {
+		fs::remove_file(path)
+	}
+
+	fn rename(&mut self, old_path: &Path, new_path: &Path) -> io::Result<()> {
+		fs::rename(old_path, new_path)
+	}
+
+	fn stat(&mut self, path: &Path) -> io::Result<FileStat> {
+		use std::os::unix::fs::FileTypeExt;
+
+		let metadata = fs::metadata(path)?;
+
+		Ok(FileStat {
+			is_directory: metadata.is_dir(),
+			is_character_special: metadata.file_type().is_char_device(),
+			size: metadata.len(),
+		})
+	}
+
+	fn mkdir(&mut self, path: &Path) -> io::Result<()> {
+		fs::create_dir(path)
+	}
+
+	fn read_dir(&mut self, path: &Path, include_hidden: bool) -> io::Result<Vec<DirEntry>> {
+		let mut entries = Vec::new();
+
+		for entry in fs::read_dir(path)? {
+			let entry = entry?;
+			let name = entry.file_name().to_string_lossy().into_owned();
+
+			if !include_hidden && name.starts_with('.') {
+				continue;
+			}
+
+			entries.push(DirEntry {
+				name,
+				is_directory: entry.file_type()?.is_dir(),
+			});
+		}
+
+		Ok(entries)
+	}
+}
+
+/// A [VfsBackend] which only allows cores to access paths inside of a set of directories on the host.
+pub struct SandboxedVfs {
+	roots: Vec<PathBuf>,
+	host: HostVfs,
+}
+
+impl SandboxedVfs {
+	/// Creates a sandbox only allowing access to the given directories (and anything inside of them).
+	pub fn new<P: AsRef<Path>>(roots: &[P]) -> io::Result<Self> {
+		let mut resolved = Vec::with_capacity(roots.len());
+
+		for root in roots {
+			resolved.push(fs::canonicalize(root)?);
+		}
+
+		Ok(Self {
+			roots: resolved,
+			host: HostVfs,
+		})
+	}
+
+	/// Resolves a path the core gave us, and makes sure it's inside the sandbox.
+	fn check(&self, path: &Path) -> io::Result<PathBuf> {
+		let resolved = resolve_path(path)?;
+
+		if self.roots.iter().any(|root| resolved.starts_with(root)) {
+			Ok(resolved)
+		} else {
+			error!("Core tried to access {} outside of the VFS sandbox", path.display());
+			Err(io::ErrorKind::PermissionDenied.into())
+		}
+	}
+}
+
+impl VfsBackend for SandboxedVfs {
+	fn open(&mut self, path: &Path, mode: OpenMode) -> io::Result<Box<dyn VfsFile>> {
+		let path = self.check(path)?;
+		self.host.open(&path, mode)
+	}
+
+	fn remove(&mut self, path: &Path) -> io::Result<()> {
+		let path = self.check(path)?;
+		self.host.remove(&path)
+	}
+
+	fn rename(&mut self, old_path: &Path, new_path: &Path) -> io::Result<()> {
+		let old_path = self.check(old_path)?;
+		let new_path = self.check(new_path)?;
+		self.host.rename(&old_path, &new_path)
+	}
+
+	fn stat(&mut self, path: &Path) -> io::Result<FileStat> {
+		let path = self.check(path)?;
+		self.host.stat(&path)
+	}
+
+	fn mkdir(&mut self, path: &Path) -> io::Result<()> {
+		let path = self.check(path)?;
+		self.host.mkdir(&path)
+	}
+
+	fn read_dir(&mut self, path: &Path, include_hidden: bool) -> io::Result<Vec<DirEntry>> {
+		let path = self.check(path)?;
+		self.host.read_dir(&path, include_hidden)
+	}
+}
+
+/// Makes a path absolute and removes any `.`/`..` components, without requiring the path to exist.
+/// The longest existing prefix of the path is canonicalized, so symlinks can't be used to escape a sandbox.
+fn resolve_path(path: &Path) -> io::Result<PathBuf> {
+	let absolute = if path.is_absolute() {
+		path.to_path_buf()
+	} else {
+		std::env::current_dir()?.join(path)
+	};
+
+	let mut normalized = PathBuf::new();
+	for component in absolute.components() {
+		match component {
+			Component::CurDir => {}
+			Component::ParentDir => {
+				normalized.pop();
+			}
+			other => normalized.push(other),
+		}
+	}
+
+	// Find the longest prefix which exists, and canonicalize that.
+	let mut existing = normalized.as_path();
+	let mut rest = Vec::new();
+
+	loop {
+		if let Ok(canonical) = fs::canonicalize(existing) {
+			let mut resolved = canonical;
+			for component in rest.iter().rev() {
+				resolved.push(component);
+			}
+			return Ok(resolved);
+		}
+
+		// A dangling symlink could point anywhere, and creating a file through it would create its target.
+		if fs::symlink_metadata(existing).is_ok() {
+			return Err(io::ErrorKind::NotFound.into());
+		}
+
+		match (existing.parent(), existing.file_name()) {
+			(Some(parent), Some(name)) => {
+				rest.push(name.to_os_string());
+				existing = parent;
+			}
+			_ => return Ok(normalized),
+		}
+	}
+}
 
 /// A file handle
 struct FileHandle {
-	/// The path used to open a file on the host
-	real_path: path::PathBuf,
+	/// The path used to open this file, as the core gave it to us
+	path: CString,
+
+	file: Box<dyn VfsFile>,
+}
+
+/// A directory handle
+struct DirHandle {
+	entries: Vec<DirEntry>,
+
+	/// Index of the current entry. [Option::None] until the first readdir() call.
+	position: Option<usize>,
+
+	/// C version of the current entry's name.
+	current_name: CString,
+}
+
+impl DirHandle {
+	fn current(&self) -> Option<&DirEntry> {
+		self.entries.get(self.position?)
+	}
 }
 
-//unsafe extern "C" fn libretro_vfs_get_path(stream: *mut )
+unsafe fn path_from_c<'a>(ptr: *const ffi::c_char) -> Option<&'a Path> {
+	if ptr.is_null() {
+		return None;
+	}
 
+	Some(Path::new(ffi::OsStr::from_bytes(
+		ffi::CStr::from_ptr(ptr).to_bytes(),
+	)))
+}
+
+unsafe extern "C" fn libretro_vfs_get_path(stream: *mut ffi::c_void) -> *const ffi::c_char {
+	match (stream as *mut FileHandle).as_ref() {
+		Some(handle) => handle.path.as_ptr(),
+		None => std::ptr::null(),
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_open(
+	path: *const ffi::c_char,
+	mode: ffi::c_uint,
+	_hints: ffi::c_uint,
+) -> *mut ffi::c_void {
+	let rust_path = match path_from_c(path) {
+		Some(path) => path,
+		None => return std::ptr::null_mut(),
+	};
+
+	let mode = OpenMode {
+		read: (mode & RETRO_VFS_FILE_ACCESS_READ) != 0,
+		write: (mode & RETRO_VFS_FILE_ACCESS_WRITE) != 0,
+		update_existing: (mode & RETRO_VFS_FILE_ACCESS_UPDATE_EXISTING) != 0,
+	};
+
+	match (*FRONTEND).vfs.open(rust_path, mode) {
+		Ok(file) => {
+			let handle = Box::new(FileHandle {
+				path: ffi::CStr::from_ptr(path).to_owned(),
+				file,
+			});
+			Box::into_raw(handle) as *mut ffi::c_void
+		}
+		Err(_) => std::ptr::null_mut(),
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_close(stream: *mut ffi::c_void) -> ffi::c_int {
+	if stream.is_null() {
+		return -1;
+	}
+
+	let mut handle = Box::from_raw(stream as *mut FileHandle);
+	match handle.file.flush() {
+		Ok(_) => 0,
+		Err(_) => -1,
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_size(stream: *mut ffi::c_void) -> i64 {
+	match (stream as *mut FileHandle).as_mut() {
+		Some(handle) => handle.file.size().map_or(-1, |size| size as i64),
+		None => -1,
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_truncate(stream: *mut ffi::c_void, length: i64) -> i64 {
+	if length < 0 {
+		return -1;
+	}
+
+	match (stream as *mut FileHandle).as_mut() {
+		Some(handle) => handle.file.truncate(length as u64).map_or(-1, |_| 0),
+		None => -1,
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_tell(stream: *mut ffi::c_void) -> i64 {
+	match (stream as *mut FileHandle).as_mut() {
+		Some(handle) => handle.file.stream_position().map_or(-1, |pos| pos as i64),
+		None => -1,
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_seek(
+	stream: *mut ffi::c_void,
+	offset: i64,
+	seek_position: ffi::c_int,
+) -> i64 {
+	let handle = match (stream as *mut FileHandle).as_mut() {
+		Some(handle) => handle,
+		None => return -1,
+	};
+
+	let seek = match seek_position {
+		RETRO_VFS_SEEK_POSITION_START if offset >= 0 => SeekFrom::Start(offset as u64),
+		RETRO_VFS_SEEK_POSITION_CURRENT => SeekFrom::Current(offset),
+		RETRO_VFS_SEEK_POSITION_END => SeekFrom::End(offset),
+		_ => return -1,
+	};
+
+	handle.file.seek(seek).map_or(-1, |pos| pos as i64)
+}
+
+unsafe extern "C" fn libretro_vfs_read(stream: *mut ffi::c_void, s: *mut ffi::c_void, len: u64) -> i64 {
+	let handle = match (stream as *mut FileHandle).as_mut() {
+		Some(handle) => handle,
+		None => return -1,
+	};
+
+	if len == 0 {
+		return 0;
+	}
+
+	let buffer = std::slice::from_raw_parts_mut(s as *mut u8, len as usize);
+
+	// Like fread(), fill as much of the buffer as we can.
+	let mut total = 0;
+	while total < buffer.len() {
+		match handle.file.read(&mut buffer[total..]) {
+			Ok(0) => break,
+			Ok(n) => total += n,
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+			Err(_) => return -1,
+		}
+	}
+
+	total as i64
+}
+
+unsafe extern "C" fn libretro_vfs_write(
+	stream: *mut ffi::c_void,
+	s: *const ffi::c_void,
+	len: u64,
+) -> i64 {
+	let handle = match (stream as *mut FileHandle).as_mut() {
+		Some(handle) => handle,
+		None => return -1,
+	};
+
+	if len == 0 {
+		return 0;
+	}
+
+	let buffer = std::slice::from_raw_parts(s as *const u8, len as usize);
+	match handle.file.write_all(buffer) {
+		Ok(_) => len as i64,
+		Err(_) => -1,
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_flush(stream: *mut ffi::c_void) -> ffi::c_int {
+	match (stream as *mut FileHandle).as_mut() {
+		Some(handle) => handle.file.flush().map_or(-1, |_| 0),
+		None => -1,
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_remove(path: *const ffi::c_char) -> ffi::c_int {
+	match path_from_c(path) {
+		Some(path) => (*FRONTEND).vfs.remove(path).map_or(-1, |_| 0),
+		None => -1,
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_rename(
+	old_path: *const ffi::c_char,
+	new_path: *const ffi::c_char,
+) -> ffi::c_int {
+	match (path_from_c(old_path), path_from_c(new_path)) {
+		(Some(old_path), Some(new_path)) => (*FRONTEND)
+			.vfs
+			.rename(old_path, new_path)
+			.map_or(-1, |_| 0),
+		_ => -1,
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_stat(path: *const ffi::c_char, size: *mut i32) -> ffi::c_int {
+	let path = match path_from_c(path) {
+		Some(path) => path,
+		None => return 0,
+	};
+
+	match (*FRONTEND).vfs.stat(path) {
+		Ok(stat) => {
+			if !size.is_null() {
+				*size = stat.size.min(i32::MAX as u64) as i32;
+			}
+
+			let mut flags = RETRO_VFS_STAT_IS_VALID;
+
+			if stat.is_directory {
+				flags |= RETRO_VFS_STAT_IS_DIRECTORY;
+			}
+
+			if stat.is_character_special {
+				flags |= RETRO_VFS_STAT_IS_CHARACTER_SPECIAL;
+			}
+
+			flags
+		}
+		Err(_) => 0,
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_mkdir(dir: *const ffi::c_char) -> ffi::c_int {
+	let path = match path_from_c(dir) {
+		Some(path) => path,
+		None => return -1,
+	};
+
+	match (*FRONTEND).vfs.mkdir(path) {
+		Ok(_) => 0,
+		Err(err) if err.kind() == io::ErrorKind::AlreadyExists => -2,
+		Err(_) => -1,
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_opendir(
+	dir: *const ffi::c_char,
+	include_hidden: bool,
+) -> *mut ffi::c_void {
+	let path = match path_from_c(dir) {
+		Some(path) => path,
+		None => return std::ptr::null_mut(),
+	};
+
+	match (*FRONTEND).vfs.read_dir(path, include_hidden) {
+		Ok(entries) => {
+			let handle = Box::new(DirHandle {
+				entries,
+				position: None,
+				current_name: CString::default(),
+			});
+			Box::into_raw(handle) as *mut ffi::c_void
+		}
+		Err(_) => std::ptr::null_mut(),
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_readdir(dirstream: *mut ffi::c_void) -> bool {
+	let handle = match (dirstream as *mut DirHandle).as_mut() {
+		Some(handle) => handle,
+		None => return false,
+	};
+
+	let next = handle.position.map_or(0, |position| position + 1);
+	handle.position = Some(next);
+
+	match handle.entries.get(next) {
+		Some(entry) => {
+			// Names with a NUL in them can't be given to the core, but that should never really happen.
+			handle.current_name = CString::new(entry.name.as_bytes()).unwrap_or_default();
+			true
+		}
+		None => false,
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_dirent_get_name(dirstream: *mut ffi::c_void) -> *const ffi::c_char {
+	match (dirstream as *mut DirHandle).as_ref() {
+		Some(handle) if handle.current().is_some() => handle.current_name.as_ptr(),
+		_ => std::ptr::null(),
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_dirent_is_dir(dirstream: *mut ffi::c_void) -> bool {
+	match (dirstream as *mut DirHandle).as_ref() {
+		Some(handle) => handle.current().is_some_and(|entry| entry.is_directory),
+		None => false,
+	}
+}
+
+unsafe extern "C" fn libretro_vfs_closedir(dirstream: *mut ffi::c_void) -> ffi::c_int {
+	if dirstream.is_null() {
+		return -1;
+	}
+
+	drop(Box::from_raw(dirstream as *mut DirHandle));
+	0
+}
+
+/// The VFS interface we give to cores.
+pub(crate) static VFS_INTERFACE: VfsInterface = VfsInterface {
+	get_path: libretro_vfs_get_path,
+	open: libretro_vfs_open,
+	close: libretro_vfs_close,
+	size: libretro_vfs_size,
+	tell: libretro_vfs_tell,
+	seek: libretro_vfs_seek,
+	read: libretro_vfs_read,
+	write: libretro_vfs_write,
+	flush: libretro_vfs_flush,
+	remove: libretro_vfs_remove,
+	rename: libretro_vfs_rename,
+	truncate: libretro_vfs_truncate,
+	stat: libretro_vfs_stat,
+	mkdir: libretro_vfs_mkdir,
+	opendir: libretro_vfs_opendir,
+	readdir: libretro_vfs_readdir,
+	dirent_get_name: libretro_vfs_dirent_get_name,
+	dirent_is_dir: libretro_vfs_dirent_is_dir,
+	closedir: libretro_vfs_closedir,
+};
+
+/// Fills in a [VfsInterfaceInfo] for a core. Returns false if the core needs a newer VFS API than we implement.
+pub(crate) unsafe fn get_vfs_interface(info: &mut VfsInterfaceInfo) -> bool {
+	if info.required_interface_version > RETRO_VFS_INTERFACE_VERSION {
+		error!(
+			"Core requires VFS API version {}, but we only implement {RETRO_VFS_INTERFACE_VERSION}",
+			info.required_interface_version
+		);
+		return false;
+	}
+
+	info.required_interface_version = RETRO_VFS_INTERFACE_VERSION;
+	info.iface = &VFS_INTERFACE;
+	true
+}
@@ -0,0 +1,340 @@
+//! Input recording and deterministic replay.
+//!
+//! A movie is a savestate taken when recording started, plus every value the core was given by
+//! the input state callback on each frame after that. Replaying loads the savestate and substitutes
+//! a [ReplayDevice] for each recorded port, which hands the core back the same values.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::input_devices::InputDevice;
+use crate::libretro_sys_new::DEVICE_MASK;
+use crate::result::{Error, Result};
+
+/// Identifies movie files.
+const MOVIE_MAGIC: &[u8; 8] = b"LPMOVIE\0";
+
+/// Current movie file format version.
+const MOVIE_VERSION: u32 = 1;
+
+/// One call to the input state callback, and what it returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InputEvent {
+	pub port: u32,
+	pub device: u32,
+	pub index: u32,
+	pub id: u32,
+	pub value: i16,
+}
+
+/// A recording of the input given to a core.
+#[derive(Clone, Debug, Default)]
+pub struct Movie {
+	/// Library name of the core the movie was recorded with.
+	pub core_name: String,
+
+	/// Name of the game the movie was recorded with.
+	pub game_name: String,
+
+	/// State of the core when recording started.
+	pub initial_state: Vec<u8>,
+
+	/// The device type plugged into each port when recording started.
+	pub ports: Vec<(u32, u32)>,
+
+	/// Input events for each frame, in the order the core asked for them.
+	pub frames: Vec<Vec<InputEvent>>,
+}
+
+fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+	writer.write_all(&value.to_le_bytes())
+}
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> Result<()> {
+	let len =
+		u32::try_from(bytes.len()).map_err(|_| Error::InvalidMovie("field is too large".into()))?;
+	write_u32(writer, len)?;
+	writer.write_all(bytes)?;
+	Ok(())
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+	let mut bytes = [0; 4];
+	reader.read_exact(&mut bytes)?;
+	Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_i16(reader: &mut impl Read) -> io::Result<i16> {
+	let mut bytes = [0; 2];
+	reader.read_exact(&mut bytes)?;
+	Ok(i16::from_le_bytes(bytes))
+}
+
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+	let len = read_u32(reader)? as usize;
+
+	// Don't trust the length enough to allocate it all up front.
+	let mut bytes = Vec::new();
+	reader.take(len as u64).read_to_end(&mut bytes)?;
+
+	if bytes.len() != len {
+		return Err(io::ErrorKind::UnexpectedEof.into());
+	}
+
+	Ok(bytes)
+}
+
+fn read_string(reader: &mut impl Read) -> Result<String> {
+	String::from_utf8(read_bytes(reader)?)
+		.map_err(|_| Error::InvalidMovie("string is not valid UTF-8".into()))
+}
+
+impl Movie {
+	/// Gets how many frames the movie lasts.
+	pub fn frame_count(&self) -> usize {
+		self.frames.len()
+	}
+
+	/// Reads a movie from the given file.
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+		Self::read_from(&mut BufReader::new(File::open(path)?))
+	}
+
+	/// Writes this movie to the given file.
+	pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		self.write_to(&mut writer)?;
+		writer.flush()?;
+		Ok(())
+	}
+
+	/// Reads a movie. All integers are little-endian, and strings and byte
+	/// arrays are prefixed with their length as a u32.
+	pub fn read_from(reader: &mut impl Read) -> Result<Self> {
+		let mut magic = [0; 8];
+		reader.read_exact(&mut magic)?;
+		if &magic != MOVIE_MAGIC {
+			return Err(Error::InvalidMovie("not a movie file".into()));
+		}
+
+		let version = read_u32(reader)?;
+		if version != MOVIE_VERSION {
+			return Err(Error::InvalidMovie(format!(
+				"unsupported version {version}"
+			)));
+		}
+
+		let core_name = read_string(reader)?;
+		let game_name = read_string(reader)?;
+		let initial_state = read_bytes(reader)?;
+
+		let port_count = read_u32(reader)?;
+		let mut ports = Vec::new();
+		for _ in 0..port_count {
+			ports.push((read_u32(reader)?, read_u32(reader)?));
+		}
+
+		let frame_count = read_u32(reader)?;
+		let mut frames = Vec::new();
+		for _ in 0..frame_count {
+			let event_count = read_u32(reader)?;
+			let mut events = Vec::new();
+
+			for _ in 0..event_count {
+				events.push(InputEvent {
+					port: read_u32(reader)?,
+					device: read_u32(reader)?,
+					index: read_u32(reader)?,
+					id: read_u32(reader)?,
+					value: read_i16(reader)?,
+				});
+			}
+
+			frames.push(events);
+		}
+
+		Ok(Self {
+			core_name,
+			game_name,
+			initial_state,
+			ports,
+			frames,
+		})
+	}
+
+	/// Writes this movie in the format [Movie::read_from] reads.
+	pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+		writer.write_all(MOVIE_MAGIC)?;
+		write_u32(writer, MOVIE_VERSION)?;
+
+		write_bytes(writer, self.core_name.as_bytes())?;
+		write_bytes(writer, self.game_name.as_bytes())?;
+		write_bytes(writer, &self.initial_state)?;
+
+		write_u32(writer, self.ports.len() as u32)?;
+		for (port, device) in &self.ports {
+			write_u32(writer, *port)?;
+			write_u32(writer, *device)?;
+		}
+
+		write_u32(writer, self.frames.len() as u32)?;
+		for events in &self.frames {
+			write_u32(writer, events.len() as u32)?;
+
+			for event in events {
+				write_u32(writer, event.port)?;
+				write_u32(writer, event.device)?;
+				write_u32(writer, event.index)?;
+				write_u32(writer, event.id)?;
+				writer.write_all(&event.value.to_le_bytes())?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// An [InputDevice] which gives back input recorded elsewhere, such as in a movie.
+/// It answers for any base device type and index it's been given input for.
+pub struct ReplayDevice {
+	device_type: u32,
+
+	/// Values keyed by (base device type, index, id).
+	inputs: HashMap<(u32, u32, u32), i16>,
+}
+
+impl ReplayDevice {
+	pub fn new(device_type: u32) -> Self {
+		Self {
+			device_type,
+			inputs: HashMap::new(),
+		}
+	}
+}
+
+impl InputDevice for ReplayDevice {
+	fn device_type(&self) -> u32 {
+		self.device_type
+	}
+
+	fn get_button(&self, id: u32) -> i16 {
+		self.get_input(self.device_type & DEVICE_MASK, 0, id)
+	}
+
+	fn reset(&mut self) {
+		self.inputs.clear();
+	}
+
+	fn press_button(&mut self, id: u32, pressure: Option<i16>) {
+		self.set_input(
+			self.device_type & DEVICE_MASK,
+			0,
+			id,
+			pressure.unwrap_or(0x7fff),
+		);
+	}
+
+	fn get_input(&self, device: u32, index: u32, id: u32) -> i16 {
+		self.inputs.get(&(device, index, id)).copied().unwrap_or(0)
+	}
+
+	fn set_input(&mut self, device: u32, index: u32, id: u32, value: i16) {
+		self.inputs.insert((device, index, id), value);
+	}
+}
+
+/// Records input into a movie as frames are run.
+pub(crate) struct MovieRecorder {
+	movie: Movie,
+	events: Vec<InputEvent>,
+}
+
+impl MovieRecorder {
+	pub fn new(movie: Movie) -> Self {
+		Self {
+			movie,
+			events: Vec::new(),
+		}
+	}
+
+	pub fn record(&mut self, event: InputEvent) {
+		self.events.push(event);
+	}
+
+	/// Called once a frame has been run.
+	pub fn end_frame(&mut self) {
+		self.movie.frames.push(std::mem::take(&mut self.events));
+	}
+
+	pub fn frame_count(&self) -> usize {
+		self.movie.frames.len()
+	}
+
+	pub fn finish(self) -> Movie {
+		self.movie
+	}
+}
+
+/// Plays a movie back through [ReplayDevice]s.
+pub(crate) struct MoviePlayer {
+	movie: Movie,
+	frame: usize,
+
+	/// Replay devices, which the core is given input from in place of the user's devices.
+	devices: HashMap<u32, ReplayDevice>,
+}
+
+impl MoviePlayer {
+	pub fn new(movie: Movie) -> Self {
+		let devices = movie
+			.ports
+			.iter()
+			.map(|(port, device)| (*port, ReplayDevice::new(*device)))
+			.collect();
+
+		Self {
+			movie,
+			frame: 0,
+			devices,
+		}
+	}
+
+	/// Gets the replay device for the given port.
+	pub fn get_device(&self, port: u32) -> Option<&dyn InputDevice> {
+		self.devices
+			.get(&port)
+			.map(|device| device as &dyn InputDevice)
+	}
+
+	/// Sets up the replay devices for the next frame. Returns false once the movie is over.
+	pub fn begin_frame(&mut self) -> bool {
+		let Some(events) = self.movie.frames.get(self.frame) else {
+			return false;
+		};
+
+		for device in self.devices.values_mut() {
+			device.reset();
+		}
+
+		for event in events {
+			if let Some(device) = self.devices.get_mut(&event.port) {
+				device.set_input(
+					event.device & DEVICE_MASK,
+					event.index,
+					event.id,
+					event.value,
+				);
+			}
+		}
+
+		self.frame += 1;
+		true
+	}
+
+	/// Gets the number of frames played so far, and the total number of frames.
+	pub fn progress(&self) -> (usize, usize) {
+		(self.frame, self.movie.frame_count())
+	}
+}
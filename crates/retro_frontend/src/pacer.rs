@@ -0,0 +1,336 @@
+//! Frame pacing for main loops.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How many recent frames statistics are calculated over.
+const STATS_WINDOW: usize = 120;
+
+/// Sleeping is imprecise, so we stop sleeping this long before a deadline and spin the rest of the way.
+const SPIN_THRESHOLD: Duration = Duration::from_millis(1);
+
+/// What the pacer does when frames run late.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CatchUpPolicy {
+	/// Run late frames back to back until caught up. If more than `max_frames` behind,
+	/// the missed frames are skipped instead.
+	CatchUp { max_frames: u32 },
+
+	/// Skip any missed frames, so that late frames only ever slow things down.
+	Skip,
+}
+
+impl Default for CatchUpPolicy {
+	fn default() -> Self {
+		Self::CatchUp { max_frames: 5 }
+	}
+}
+
+/// How fast emulation runs, relative to the core's frame rate.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Speed {
+	#[default]
+	Normal,
+
+	/// Runs faster by the given multiplier. [f64::INFINITY] runs as fast as possible.
+	FastForward(f64),
+
+	/// Runs slower by the given multiplier (e.g. 0.5 for half speed).
+	SlowMotion(f64),
+}
+
+impl Speed {
+	/// Gets how many times faster than normal this speed is.
+	pub fn multiplier(self) -> f64 {
+		match self {
+			Self::Normal => 1.0,
+			Self::FastForward(multiplier) | Self::SlowMotion(multiplier) => multiplier,
+		}
+	}
+}
+
+/// Statistics about recent frames.
+#[derive(Clone, Debug, Default)]
+pub struct PacerStats {
+	/// Achieved frames per second.
+	pub fps: f64,
+
+	/// Average, minimum and maximum time spent running a frame (not counting time spent waiting).
+	pub frame_time_avg: Duration,
+	pub frame_time_min: Duration,
+	pub frame_time_max: Duration,
+
+	/// Total frames paced.
+	pub frames: u64,
+
+	/// Total frames which missed their deadline.
+	pub late_frames: u64,
+
+	/// Total frames skipped to get back on schedule.
+	pub skipped_frames: u64,
+}
+
+/// Paces a main loop to a frame rate using absolute deadlines, so that time spent running
+/// frames (and imprecise sleeps) doesn't cause drift.
+pub struct FramePacer {
+	fps: f64,
+	speed: Speed,
+	frame_duration: Duration,
+	policy: CatchUpPolicy,
+
+	/// When the next frame should start. [None] until the first frame is paced.
+	next_deadline: Option<Instant>,
+
+	/// When the last wait returned, which is when the caller started running its frame.
+	last_wake: Option<Instant>,
+
+	/// Recent (time between frames, time spent running the frame) pairs.
+	history: VecDeque<(Duration, Duration)>,
+
+	frames: u64,
+	late_frames: u64,
+	skipped_frames: u64,
+}
+
+impl FramePacer {
+	/// Creates a pacer for the given frame rate.
+	pub fn new(fps: f64, policy: CatchUpPolicy) -> Self {
+		Self {
+			fps,
+			speed: Speed::Normal,
+			frame_duration: Self::frame_duration(fps, Speed::Normal),
+			policy,
+			next_deadline: None,
+			last_wake: None,
+			history: VecDeque::with_capacity(STATS_WINDOW),
+			frames: 0,
+			late_frames: 0,
+			skipped_frames: 0,
+		}
+	}
+
+	fn frame_duration(fps: f64, speed: Speed) -> Duration {
+		// Guard against cores giving nonsense rates.
+		let fps = if fps.is_finite() && fps > 0.0 {
+			fps
+		} else {
+			60.0
+		};
+		let multiplier = speed.multiplier();
+
+		if multiplier.is_infinite() {
+			return Duration::ZERO;
+		}
+
+		if multiplier > 0.0 {
+			Duration::from_secs_f64(1.0 / (fps * multiplier))
+		} else {
+			Duration::from_secs_f64(1.0 / fps)
+		}
+	}
+
+	/// Changes the frame rate. This takes effect from the next frame.
+	pub fn set_fps(&mut self, fps: f64) {
+		self.fps = fps;
+		self.frame_duration = Self::frame_duration(self.fps, self.speed);
+	}
+
+	/// Changes the speed frames are paced at. This takes effect from the next frame.
+	pub fn set_speed(&mut self, speed: Speed) {
+		if speed != self.speed {
+			// Don't try and catch up on frames missed at a different speed.
+			self.next_deadline = None;
+		}
+
+		self.speed = speed;
+		self.frame_duration = Self::frame_duration(self.fps, self.speed);
+	}
+
+	pub fn set_policy(&mut self, policy: CatchUpPolicy) {
+		self.policy = policy;
+	}
+
+	/// Forgets the schedule, e.g. after being paused. The next frame starts immediately.
+	pub fn reset(&mut self) {
+		self.next_deadline = None;
+		self.last_wake = None;
+	}
+
+	/// Waits until the next frame should be run. Call this once after running every frame.
+	pub fn wait(&mut self) {
+		let now = Instant::now();
+
+		let deadline = match self.next_deadline {
+			Some(deadline) => deadline + self.frame_duration,
+			None => now + self.frame_duration,
+		};
+
+		self.frames += 1;
+
+		let deadline = if self.frame_duration.is_zero() {
+			// Running unthrottled.
+			now
+		} else if now > deadline {
+			self.late_frames += 1;
+			self.resync_late(now, deadline)
+		} else {
+			sleep_until(deadline);
+			deadline
+		};
+
+		self.next_deadline = Some(deadline);
+
+		let wake = Instant::now();
+		if let Some(last_wake) = self.last_wake {
+			if self.history.len() == STATS_WINDOW {
+				self.history.pop_front();
+			}
+
+			self.history.push_back((wake - last_wake, now - last_wake));
+		}
+
+		self.last_wake = Some(wake);
+	}
+
+	/// Works out the next deadline when running behind schedule.
+	fn resync_late(&mut self, now: Instant, deadline: Instant) -> Instant {
+		let behind = ((now - deadline).as_secs_f64() / self.frame_duration.as_secs_f64()) as u64;
+
+		let max_frames = match self.policy {
+			CatchUpPolicy::CatchUp { max_frames } => max_frames as u64,
+			CatchUpPolicy::Skip => 0,
+		};
+
+		if behind < max_frames {
+			// Keep the deadline in the past; the caller will run frames back to back until caught up.
+			return deadline;
+		}
+
+		self.skipped_frames += behind;
+		now
+	}
+
+	/// Gets statistics about recent frames.
+	pub fn stats(&self) -> PacerStats {
+		let mut stats = PacerStats {
+			frames: self.frames,
+			late_frames: self.late_frames,
+			skipped_frames: self.skipped_frames,
+			..Default::default()
+		};
+
+		if self.history.is_empty() {
+			return stats;
+		}
+
+		let total: Duration = self.history.iter().map(|(period, _)| *period).sum();
+		let work: Duration = self.history.iter().map(|(_, work)| *work).sum();
+
+		if !total.is_zero() {
+			stats.fps = self.history.len() as f64 / total.as_secs_f64();
+		}
+
+		stats.frame_time_avg = work / self.history.len() as u32;
+		stats.frame_time_min = self
+			.history
+			.iter()
+			.map(|(_, work)| *work)
+			.min()
+			.unwrap_or_default();
+		stats.frame_time_max = self
+			.history
+			.iter()
+			.map(|(_, work)| *work)
+			.max()
+			.unwrap_or_default();
+		stats
+	}
+}
+
+/// Sleeps until the given instant, spinning for the last bit to be precise.
+fn sleep_until(deadline: Instant) {
+	let now = Instant::now();
+	if deadline <= now {
+		return;
+	}
+
+	let remaining = deadline - now;
+	if remaining > SPIN_THRESHOLD {
+		std::thread::sleep(remaining - SPIN_THRESHOLD);
+	}
+
+	while Instant::now() < deadline {
+		std::thread::yield_now();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const FRAME: Duration = Duration::from_millis(10);
+
+	#[test]
+	fn late_frames_are_caught_up() {
+		let mut pacer = FramePacer::new(100.0, CatchUpPolicy::CatchUp { max_frames: 5 });
+		let deadline = Instant::now();
+
+		// The deadline stays in the past, so the missed frames are run back to back.
+		assert_eq!(pacer.resync_late(deadline + FRAME * 2, deadline), deadline);
+		assert_eq!(pacer.skipped_frames, 0);
+
+		// Too far behind to catch up.
+		let now = deadline + FRAME * 7;
+		assert_eq!(pacer.resync_late(now, deadline), now);
+		assert_eq!(pacer.skipped_frames, 7);
+	}
+
+	#[test]
+	fn late_frames_are_skipped() {
+		let mut pacer = FramePacer::new(100.0, CatchUpPolicy::Skip);
+		let deadline = Instant::now();
+
+		let now = deadline + FRAME * 2;
+		assert_eq!(pacer.resync_late(now, deadline), now);
+		assert_eq!(pacer.skipped_frames, 2);
+	}
+
+	#[test]
+	fn stats_are_calculated_over_history() {
+		let mut pacer = FramePacer::new(100.0, CatchUpPolicy::default());
+
+		let stats = pacer.stats();
+		assert_eq!(stats.frames, 0);
+		assert_eq!(stats.fps, 0.0);
+		assert_eq!(stats.frame_time_avg, Duration::ZERO);
+
+		pacer.frames = 3;
+		pacer.late_frames = 1;
+		pacer.skipped_frames = 2;
+		pacer.history.extend([
+			(Duration::from_millis(10), Duration::from_millis(4)),
+			(Duration::from_millis(30), Duration::from_millis(8)),
+		]);
+
+		let stats = pacer.stats();
+		assert_eq!(stats.frames, 3);
+		assert_eq!(stats.late_frames, 1);
+		assert_eq!(stats.skipped_frames, 2);
+		assert_eq!(stats.fps, 50.0);
+		assert_eq!(stats.frame_time_avg, Duration::from_millis(6));
+		assert_eq!(stats.frame_time_min, Duration::from_millis(4));
+		assert_eq!(stats.frame_time_max, Duration::from_millis(8));
+	}
+
+	#[test]
+	fn frame_rate_changes_are_applied() {
+		let mut pacer = FramePacer::new(100.0, CatchUpPolicy::default());
+		assert_eq!(pacer.frame_duration, FRAME);
+
+		pacer.set_fps(50.0);
+		assert_eq!(pacer.frame_duration, FRAME * 2);
+
+		pacer.set_speed(Speed::FastForward(2.0));
+		assert_eq!(pacer.frame_duration, FRAME);
+	}
+}
@@ -0,0 +1,731 @@
+//! Running frontends in child processes.
+//!
+//! Only one [Frontend](crate::frontend::Frontend) can exist in a process, since libretro cores are
+//! full of global state. A [RemoteFrontend] runs one in a runner process instead, so any number of
+//! them can run at once. The runner is any program which calls [run_runner]; requests are sent to it
+//! over a socket, and video frames come back through shared memory.
+//!
+//! This also keeps a core which crashes from taking the rest of the program down with it.
+//! Crashes are reported as [Error::CoreCrashed], and the runner can be restarted from the most
+//! recent save state, either with [RemoteFrontend::restart] or automatically.
+
+mod protocol;
+mod runner;
+mod shm;
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::os::fd::{AsRawFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use libretro_sys::{
+	GameGeometry, SystemAvInfo, SystemTiming, DEVICE_ANALOG, DEVICE_JOYPAD, DEVICE_LIGHTGUN,
+	DEVICE_MOUSE, DEVICE_POINTER,
+};
+use tracing::{error, info, warn};
+
+use crate::audio::AudioConfig;
+use crate::frontend::{FrontendConfig, FrontendInterface};
+use crate::input_devices::InputDevice;
+use crate::libretro_input_info::{ControllerType, InputDescriptor};
+use crate::result::{Error, Result};
+use protocol::{Event, InputValue, PortInput, Reply, Request, Response};
+use shm::SharedMemory;
+
+pub use runner::run_runner;
+
+/// File descriptor the runner is given its end of the socket on.
+const RUNNER_IPC_FD: RawFd = 3;
+
+/// File descriptor the runner is given the shared video buffer on.
+const RUNNER_VIDEO_FD: RawFd = 4;
+
+/// Size of the shared video buffer, in pixels. This is far bigger than any core should need.
+const VIDEO_BUFFER_PIXELS: usize = 4096 * 4096;
+
+/// Base device types sent to the runner for each device, and how many indices they have.
+const INPUT_DEVICES: [(u32, u32); 5] = [
+	(DEVICE_JOYPAD, 1),
+	(DEVICE_MOUSE, 1),
+	(DEVICE_LIGHTGUN, 1),
+	(DEVICE_ANALOG, 3),
+	(DEVICE_POINTER, 1),
+];
+
+/// Input ids sent to the runner for each device type and index.
+const INPUT_IDS: u32 = 32;
+
+/// How long to wait for the runner to shut down before killing it.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the runner has to handle a request by default, before it's assumed to have hung.
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How many save states to remember the frames of.
+const STATE_FRAMES: usize = 64;
+
+/// Why a runner process died.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrashReason {
+	/// The runner was killed by a signal, such as `SIGSEGV` or `SIGABRT`.
+	Signal(i32),
+
+	/// The runner exited by itself, which it only does if something went badly wrong.
+	Exited(i32),
+
+	/// The runner stopped responding, and was killed.
+	TimedOut,
+
+	/// The runner sent something which couldn't be understood, and was killed.
+	InvalidReply,
+
+	/// The runner's exit status couldn't be found.
+	Unknown,
+}
+
+impl From<ExitStatus> for CrashReason {
+	fn from(status: ExitStatus) -> Self {
+		if let Some(signal) = status.signal() {
+			Self::Signal(signal)
+		} else if let Some(code) = status.code() {
+			Self::Exited(code)
+		} else {
+			Self::Unknown
+		}
+	}
+}
+
+impl fmt::Display for CrashReason {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Signal(signal) => write!(f, "killed by signal {signal}"),
+			Self::Exited(code) => write!(f, "exited with status {code}"),
+			Self::TimedOut => write!(f, "stopped responding"),
+			Self::InvalidReply => write!(f, "sent an invalid reply"),
+			Self::Unknown => write!(f, "unknown reason"),
+		}
+	}
+}
+
+/// Information about a core crash.
+#[derive(Clone, Debug)]
+pub struct CrashInfo {
+	/// Library name of the core, if one was loaded.
+	pub core_name: Option<String>,
+
+	/// The last frame which finished before the crash, counted from when the game was loaded.
+	pub frame: u64,
+
+	pub reason: CrashReason,
+}
+
+impl fmt::Display for CrashInfo {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let core_name = self.core_name.as_deref().unwrap_or("runner");
+		write!(
+			f,
+			"{core_name} crashed after frame {} ({})",
+			self.frame, self.reason
+		)
+	}
+}
+
+/// The game loaded into the runner.
+#[derive(Clone)]
+enum LoadedGame {
+	File(PathBuf),
+	NoGame,
+}
+
+/// Everything needed to get a new runner back to where the old one was.
+#[derive(Default)]
+struct Session {
+	core_path: Option<PathBuf>,
+	core_name: Option<String>,
+	game: Option<LoadedGame>,
+	variables: HashMap<String, String>,
+	audio_config: Option<AudioConfig>,
+
+	/// Frames run since the game was loaded.
+	frame: u64,
+
+	/// The most recent save state saved or loaded, and the frame it was taken on.
+	latest_state: Option<(u64, Vec<u8>)>,
+
+	/// Hashes of recent save states, and the frames they were taken on.
+	state_frames: VecDeque<(u64, u64)>,
+}
+
+/// A frontend running in a child process.
+pub struct RemoteFrontend {
+	command: Command,
+	config: FrontendConfig,
+
+	/// The descriptor of the runner's end of the socket, for the command to pick up.
+	runner_socket_fd: Arc<AtomicI32>,
+
+	child: Child,
+	socket: UnixStream,
+	video: SharedMemory,
+
+	interface: Box<dyn FrontendInterface>,
+	input_devices: HashMap<u32, Box<dyn InputDevice>>,
+
+	session: Session,
+
+	/// Set once the runner has crashed, until it's restarted.
+	crash: Option<CrashInfo>,
+
+	/// How often save states are taken to restart from, if runners are restarted after crashing.
+	auto_restart_interval: Option<u32>,
+	restarting: bool,
+
+	request_timeout: Option<Duration>,
+}
+
+fn check(ret: libc::c_int) -> std::io::Result<libc::c_int> {
+	if ret == -1 {
+		return Err(std::io::Error::last_os_error());
+	}
+
+	Ok(ret)
+}
+
+/// Gets the state of every button/axis of a device which isn't zero.
+fn snapshot_input(device: &dyn InputDevice) -> Vec<InputValue> {
+	let mut values = Vec::new();
+
+	for (device_type, indices) in INPUT_DEVICES {
+		for index in 0..indices {
+			for id in 0..INPUT_IDS {
+				let value = device.get_input(device_type, index, id);
+
+				if value != 0 {
+					values.push(InputValue {
+						device: device_type,
+						index,
+						id,
+						value,
+					});
+				}
+			}
+		}
+	}
+
+	values
+}
+
+fn hash_state(state: &[u8]) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	state.hash(&mut hasher);
+	hasher.finish()
+}
+
+/// Starts a runner, and gives it the frontend config.
+fn start_runner(
+	command: &mut Command,
+	runner_socket_fd: &AtomicI32,
+	config: &FrontendConfig,
+	request_timeout: Option<Duration>,
+) -> Result<(Child, UnixStream)> {
+	let (mut socket, runner_socket) = UnixStream::pair()?;
+	socket.set_read_timeout(request_timeout)?;
+	socket.set_write_timeout(request_timeout)?;
+
+	runner_socket_fd.store(runner_socket.as_raw_fd(), Ordering::SeqCst);
+	let child = command.spawn()?;
+	drop(runner_socket);
+
+	protocol::write_config(&mut socket, config)?;
+	Ok((child, socket))
+}
+
+impl RemoteFrontend {
+	/// Spawns a runner with the given command, which must end up calling [run_runner].
+	/// The runner's frontend uses the directories in `config`, and calls `interface` as an
+	/// in-process frontend would. Cores using hardware OpenGL rendering can't be run.
+	pub fn spawn(
+		mut command: Command,
+		interface: Box<dyn FrontendInterface>,
+		config: &FrontendConfig,
+	) -> Result<Self> {
+		let video = SharedMemory::new(VIDEO_BUFFER_PIXELS)?;
+		let runner_socket_fd = Arc::new(AtomicI32::new(-1));
+
+		let video_fd = video.as_raw_fd();
+		let socket_fd = runner_socket_fd.clone();
+
+		// SAFETY: Only async-signal-safe functions are called.
+		unsafe {
+			command.pre_exec(move || {
+				// Move the descriptors out of the way first, in case either is already
+				// sitting where the other needs to go.
+				let socket_fd = socket_fd.load(Ordering::SeqCst);
+				let socket_fd = check(libc::fcntl(socket_fd, libc::F_DUPFD_CLOEXEC, 10))?;
+				let video_fd = check(libc::fcntl(video_fd, libc::F_DUPFD_CLOEXEC, 10))?;
+
+				check(libc::dup2(socket_fd, RUNNER_IPC_FD))?;
+				check(libc::dup2(video_fd, RUNNER_VIDEO_FD))?;
+				Ok(())
+			});
+		}
+
+		let (child, socket) = start_runner(
+			&mut command,
+			&runner_socket_fd,
+			config,
+			Some(DEFAULT_REQUEST_TIMEOUT),
+		)?;
+
+		Ok(Self {
+			command,
+			config: config.clone(),
+			runner_socket_fd,
+			child,
+			socket,
+			video,
+			interface,
+			input_devices: HashMap::new(),
+			session: Session::default(),
+			crash: None,
+			auto_restart_interval: None,
+			restarting: false,
+			request_timeout: Some(DEFAULT_REQUEST_TIMEOUT),
+		})
+	}
+
+	/// Sets how long the runner has to handle a request. If it takes any longer, the core is
+	/// assumed to have hung, and the runner is killed and treated as crashed. With [None],
+	/// requests can take forever. This defaults to 30 seconds.
+	pub fn set_request_timeout(&mut self, timeout: Option<Duration>) -> Result<()> {
+		self.socket.set_read_timeout(timeout)?;
+		self.socket.set_write_timeout(timeout)?;
+		self.request_timeout = timeout;
+		Ok(())
+	}
+
+	/// Sends a request to the runner, and gives the interface everything
+	/// the runner's frontend output while handling it.
+	fn request(&mut self, request: Request) -> Result<Reply> {
+		if let Some(crash) = self.crash.as_ref() {
+			return Err(Error::CoreCrashed(crash.clone()));
+		}
+
+		let response = match request
+			.write_to(&mut self.socket)
+			.and_then(|_| Response::read_from(&mut self.socket))
+		{
+			Ok(response) => response,
+			Err(err) => {
+				let reason = match err {
+					Error::IoError(err)
+						if matches!(
+							err.kind(),
+							std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+						) =>
+					{
+						Some(CrashReason::TimedOut)
+					}
+					// The only other way the socket breaks is if the runner died.
+					Error::IoError(_) => None,
+					// The rest of the reply can't be found, so nothing after it can be read either.
+					err => {
+						error!("Invalid reply from runner: {err}");
+						Some(CrashReason::InvalidReply)
+					}
+				};
+
+				return Err(self.crashed(reason));
+			}
+		};
+
+		for event in response.events {
+			match event {
+				Event::VideoResize { width, height } => self.interface.video_resize(width, height),
+				Event::VideoFrame { pitch, len } => {
+					let Some(frame) = self.video.as_slice().get(..len as usize) else {
+						error!("Runner sent a video frame of {len} pixels, which is too big");
+						return Err(self.crashed(Some(CrashReason::InvalidReply)));
+					};
+
+					self.interface.video_update(frame, pitch);
+				}
+				Event::Audio(samples) => self.interface.audio_sample(&samples, samples.len() / 2),
+			}
+		}
+
+		match response.reply {
+			Reply::Error(err) => Err(err),
+			reply => Ok(reply),
+		}
+	}
+
+	/// Sends a request which doesn't give back anything.
+	fn request_ok(&mut self, request: Request) -> Result<()> {
+		match self.request(request)? {
+			Reply::Ok => Ok(()),
+			_ => Err(Error::RunnerProtocol("unexpected reply".into())),
+		}
+	}
+
+	/// Collects the dead runner, and restarts it if auto restarting is enabled. `reason` is
+	/// given if the runner is being killed for a reason of our own, rather than having died.
+	/// Gives back the error to report.
+	fn crashed(&mut self, reason: Option<CrashReason>) -> Error {
+		// The runner may have only closed its socket, so make sure it's really gone.
+		let _ = self.child.kill();
+		let status = self
+			.child
+			.wait()
+			.map_or(CrashReason::Unknown, CrashReason::from);
+		let reason = reason.unwrap_or(status);
+
+		let crash = CrashInfo {
+			core_name: self.session.core_name.clone(),
+			frame: self.session.frame,
+			reason,
+		};
+
+		error!("Core crashed: {crash}");
+		self.crash = Some(crash.clone());
+
+		if self.auto_restart_interval.is_some() && !self.restarting {
+			if let Err(err) = self.restart() {
+				error!("Could not restart runner: {err}");
+			}
+		}
+
+		Error::CoreCrashed(crash)
+	}
+
+	/// Gets the process ID of the runner.
+	pub fn id(&self) -> u32 {
+		self.child.id()
+	}
+
+	/// Gets information about the last crash, if the runner has crashed and hasn't been restarted.
+	pub fn get_crash(&self) -> Option<&CrashInfo> {
+		self.crash.as_ref()
+	}
+
+	/// Restarts the runner from the most recent save state, loading the same core and game and
+	/// setting the same variables. Without a save state, the game starts from the beginning.
+	pub fn restart(&mut self) -> Result<()> {
+		let _ = self.child.kill();
+		let _ = self.child.wait();
+
+		let (child, socket) = start_runner(
+			&mut self.command,
+			&self.runner_socket_fd,
+			&self.config,
+			self.request_timeout,
+		)?;
+		self.child = child;
+		self.socket = socket;
+		self.crash = None;
+
+		self.restarting = true;
+		let result = self.restore_session();
+		self.restarting = false;
+
+		if result.is_ok() {
+			info!(
+				"Restarted runner from frame {} (pid {})",
+				self.session.frame,
+				self.id()
+			);
+		}
+
+		result
+	}
+
+	fn restore_session(&mut self) -> Result<()> {
+		let Some(core_path) = self.session.core_path.clone() else {
+			return Ok(());
+		};
+		self.request(Request::LoadCore(core_path))?;
+
+		let variables: Vec<_> = self.session.variables.clone().into_iter().collect();
+		for (key, value) in variables {
+			self.request_ok(Request::SetVariable { key, value })?;
+		}
+
+		self.request_ok(Request::SetAudioConfig(self.session.audio_config.clone()))?;
+
+		match self.session.game.clone() {
+			Some(LoadedGame::File(path)) => self.request_ok(Request::LoadGame(path))?,
+			Some(LoadedGame::NoGame) => self.request_ok(Request::LoadNoGame)?,
+			None => return Ok(()),
+		}
+
+		self.session.frame = 0;
+		if let Some((frame, state)) = self.session.latest_state.clone() {
+			self.request_ok(Request::LoadState(state))?;
+			self.session.frame = frame;
+		}
+
+		Ok(())
+	}
+
+	/// Restarts the runner automatically when it crashes, taking a save state to restart from
+	/// every `interval` frames. [Error::CoreCrashed] is still returned for the request the
+	/// crash happened during.
+	pub fn enable_auto_restart(&mut self, interval: u32) {
+		self.auto_restart_interval = Some(interval.max(1));
+	}
+
+	pub fn disable_auto_restart(&mut self) {
+		self.auto_restart_interval = None;
+	}
+
+	/// Plugs in an input device to the specified port, replacing any device already plugged into it.
+	/// The runner sees the change when the next frame is run.
+	pub fn plug_input_device(&mut self, port: u32, device: Box<dyn InputDevice>) {
+		self.input_devices.insert(port, device);
+	}
+
+	/// Unplugs a input device from the given port, giving it back.
+	pub fn unplug_input_device(&mut self, port: u32) -> Option<Box<dyn InputDevice>> {
+		self.input_devices.remove(&port)
+	}
+
+	/// Gets the input device plugged into the given port.
+	pub fn get_input_device(&mut self, port: u32) -> Option<&mut (dyn InputDevice + 'static)> {
+		self.input_devices
+			.get_mut(&port)
+			.map(|device| device.as_mut())
+	}
+
+	pub fn load_core<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+		let path = path.as_ref().to_path_buf();
+
+		match self.request(Request::LoadCore(path.clone()))? {
+			Reply::CoreLoaded { name } => {
+				self.session = Session {
+					core_path: Some(path),
+					core_name: Some(name),
+					..Default::default()
+				};
+				Ok(())
+			}
+			_ => Err(Error::RunnerProtocol("unexpected reply".into())),
+		}
+	}
+
+	pub fn unload_core(&mut self) -> Result<()> {
+		self.request_ok(Request::UnloadCore)?;
+		self.session = Session::default();
+		Ok(())
+	}
+
+	/// Gets the library name of the loaded core.
+	pub fn get_core_name(&self) -> Option<&str> {
+		self.session.core_name.as_deref()
+	}
+
+	pub fn load_game<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+		let path = path.as_ref().to_path_buf();
+		self.request_ok(Request::LoadGame(path.clone()))?;
+		self.game_loaded(LoadedGame::File(path));
+		Ok(())
+	}
+
+	pub fn load_no_game(&mut self) -> Result<()> {
+		self.request_ok(Request::LoadNoGame)?;
+		self.game_loaded(LoadedGame::NoGame);
+		Ok(())
+	}
+
+	fn game_loaded(&mut self, game: LoadedGame) {
+		self.session.game = Some(game);
+		self.session.frame = 0;
+		self.session.latest_state = None;
+		self.session.state_frames.clear();
+	}
+
+	pub fn unload_game(&mut self) -> Result<()> {
+		self.request_ok(Request::UnloadGame)?;
+		self.session.game = None;
+		self.session.frame = 0;
+		self.session.latest_state = None;
+		self.session.state_frames.clear();
+		Ok(())
+	}
+
+	pub fn reset(&mut self) -> Result<()> {
+		self.request_ok(Request::Reset)
+	}
+
+	/// Gets the number of frames run since the game was loaded. Loading a save state goes back to
+	/// the frame it was taken on, if it was one of the last 64 states saved.
+	pub fn get_frame(&self) -> u64 {
+		self.session.frame
+	}
+
+	/// Runs a frame. Input is polled from the interface first, and
+	/// the state of every plugged in device is sent to the runner.
+	pub fn run_frame(&mut self) -> Result<()> {
+		self.interface.input_poll(&mut self.input_devices);
+
+		let input = self
+			.input_devices
+			.iter()
+			.map(|(port, device)| PortInput {
+				port: *port,
+				device: device.device_type(),
+				values: snapshot_input(device.as_ref()),
+			})
+			.collect();
+
+		self.request_ok(Request::RunFrame(input))?;
+		self.session.frame += 1;
+
+		if let Some(interval) = self.auto_restart_interval {
+			if self.session.frame.is_multiple_of(interval as u64) {
+				if let Err(err) = self.save_state() {
+					warn!(
+						"Could not take save state to restart from, disabling auto restart: {err}"
+					);
+					self.auto_restart_interval = None;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Saves the core's state. This also becomes the state the runner is restarted from.
+	pub fn save_state(&mut self) -> Result<Vec<u8>> {
+		match self.request(Request::SaveState)? {
+			Reply::State(state) => {
+				if self.session.state_frames.len() == STATE_FRAMES {
+					self.session.state_frames.pop_front();
+				}
+
+				self.session
+					.state_frames
+					.push_back((hash_state(&state), self.session.frame));
+				self.session.latest_state = Some((self.session.frame, state.clone()));
+				Ok(state)
+			}
+			_ => Err(Error::RunnerProtocol("unexpected reply".into())),
+		}
+	}
+
+	/// Loads a save state. This also becomes the state the runner is restarted from.
+	pub fn load_state(&mut self, data: &[u8]) -> Result<()> {
+		self.request_ok(Request::LoadState(data.to_vec()))?;
+
+		// States we don't know the frame of carry on counting from the current frame.
+		let hash = hash_state(data);
+		if let Some((_, frame)) = self
+			.session
+			.state_frames
+			.iter()
+			.rev()
+			.find(|(state_hash, _)| *state_hash == hash)
+		{
+			self.session.frame = *frame;
+		}
+
+		self.session.latest_state = Some((self.session.frame, data.to_vec()));
+		Ok(())
+	}
+
+	pub fn get_av_info(&mut self) -> Result<SystemAvInfo> {
+		match self.request(Request::GetAvInfo)? {
+			Reply::AvInfo(info) => Ok(SystemAvInfo {
+				geometry: GameGeometry {
+					base_width: info.base_width,
+					base_height: info.base_height,
+					max_width: info.max_width,
+					max_height: info.max_height,
+					aspect_ratio: info.aspect_ratio,
+				},
+				timing: SystemTiming {
+					fps: info.fps,
+					sample_rate: info.sample_rate,
+				},
+			}),
+			_ => Err(Error::RunnerProtocol("unexpected reply".into())),
+		}
+	}
+
+	/// Gets what the core says each button and axis does in the loaded game.
+	pub fn get_input_descriptors(&mut self) -> Result<Vec<InputDescriptor>> {
+		match self.request(Request::GetInputDescriptors)? {
+			Reply::InputDescriptors(descriptors) => Ok(descriptors),
+			_ => Err(Error::RunnerProtocol("unexpected reply".into())),
+		}
+	}
+
+	/// Gets the description of a button or axis, e.g. "Jump". `device` is the base device type.
+	pub fn get_input_description(
+		&mut self,
+		port: u32,
+		device: u32,
+		index: u32,
+		id: u32,
+	) -> Result<Option<String>> {
+		Ok(self
+			.get_input_descriptors()?
+			.into_iter()
+			.find(|desc| {
+				desc.port == port && desc.device == device && desc.index == index && desc.id == id
+			})
+			.map(|desc| desc.description))
+	}
+
+	/// Gets the controller types the core supports on the given port. If this is empty,
+	/// the core didn't say, and any device can be plugged in.
+	pub fn get_controller_types(&mut self, port: u32) -> Result<Vec<ControllerType>> {
+		match self.request(Request::GetControllerTypes(port))? {
+			Reply::ControllerTypes(types) => Ok(types),
+			_ => Err(Error::RunnerProtocol("unexpected reply".into())),
+		}
+	}
+
+	/// Sets a core variable. See [Frontend::set_variable](crate::frontend::Frontend::set_variable).
+	pub fn set_variable(&mut self, key: &str, value: &str) -> Result<()> {
+		self.request_ok(Request::SetVariable {
+			key: key.into(),
+			value: value.into(),
+		})?;
+
+		self.session.variables.insert(key.into(), value.into());
+		Ok(())
+	}
+
+	/// Sets how audio from the core is resampled and chunked. See
+	/// [Frontend::set_audio_config](crate::frontend::Frontend::set_audio_config).
+	pub fn set_audio_config(&mut self, config: Option<AudioConfig>) -> Result<()> {
+		self.request_ok(Request::SetAudioConfig(config.clone()))?;
+		self.session.audio_config = config;
+		Ok(())
+	}
+}
+
+impl Drop for RemoteFrontend {
+	fn drop(&mut self) {
+		let _ = self.socket.set_read_timeout(Some(SHUTDOWN_TIMEOUT));
+
+		let shut_down = self.crash.is_none()
+			&& Request::Shutdown
+				.write_to(&mut self.socket)
+				.and_then(|_| Response::read_from(&mut self.socket))
+				.is_ok();
+
+		if !shut_down {
+			let _ = self.child.kill();
+		}
+
+		let _ = self.child.wait();
+	}
+}
@@ -0,0 +1,635 @@
+//! Messages sent between a [super::RemoteFrontend] and its runner process.
+//!
+//! Every message is a little-endian u32 length followed by that many bytes. The parent sends
+//! a [Request] and waits for a single [Response] before sending another one.
+
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use crate::audio::AudioConfig;
+use crate::frontend::FrontendConfig;
+use crate::libretro_input_info::{ControllerType, InputDescriptor};
+use crate::result::{Error, Result};
+
+/// The largest message we'll accept. Save states are the biggest thing sent.
+const MAX_MESSAGE_SIZE: usize = 256 * 1024 * 1024;
+
+/// The state of one button/axis, as the core would ask for it.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct InputValue {
+	/// Base device type.
+	pub device: u32,
+	pub index: u32,
+	pub id: u32,
+	pub value: i16,
+}
+
+/// The state of the input device plugged into a port.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PortInput {
+	pub port: u32,
+	pub device: u32,
+
+	/// Every button/axis which isn't zero.
+	pub values: Vec<InputValue>,
+}
+
+pub(crate) enum Request {
+	LoadCore(PathBuf),
+	UnloadCore,
+	LoadGame(PathBuf),
+	LoadNoGame,
+	UnloadGame,
+	Reset,
+	RunFrame(Vec<PortInput>),
+	SaveState,
+	LoadState(Vec<u8>),
+	GetAvInfo,
+	SetVariable { key: String, value: String },
+	SetAudioConfig(Option<AudioConfig>),
+	Shutdown,
+	GetInputDescriptors,
+	GetControllerTypes(u32),
+}
+
+/// Something the runner's frontend gave its interface while handling a request.
+pub(crate) enum Event {
+	VideoResize {
+		width: u32,
+		height: u32,
+	},
+
+	/// A video frame of `len` pixels, which is in the shared video buffer.
+	VideoFrame {
+		pitch: u32,
+		len: u32,
+	},
+
+	Audio(Vec<i16>),
+}
+
+pub(crate) struct AvInfo {
+	pub base_width: u32,
+	pub base_height: u32,
+	pub max_width: u32,
+	pub max_height: u32,
+	pub aspect_ratio: f32,
+	pub fps: f64,
+	pub sample_rate: f64,
+}
+
+pub(crate) enum Reply {
+	Ok,
+	Error(Error),
+	State(Vec<u8>),
+	AvInfo(AvInfo),
+	CoreLoaded { name: String },
+	InputDescriptors(Vec<InputDescriptor>),
+	ControllerTypes(Vec<ControllerType>),
+}
+
+pub(crate) struct Response {
+	pub events: Vec<Event>,
+	pub reply: Reply,
+}
+
+/// Builds up a message.
+#[derive(Default)]
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+	fn u8(&mut self, value: u8) {
+		self.0.push(value);
+	}
+
+	fn i16(&mut self, value: i16) {
+		self.0.extend_from_slice(&value.to_le_bytes());
+	}
+
+	fn u32(&mut self, value: u32) {
+		self.0.extend_from_slice(&value.to_le_bytes());
+	}
+
+	fn u64(&mut self, value: u64) {
+		self.0.extend_from_slice(&value.to_le_bytes());
+	}
+
+	fn f64(&mut self, value: f64) {
+		self.u64(value.to_bits());
+	}
+
+	fn len(&mut self, len: usize) {
+		self.u32(len as u32);
+	}
+
+	fn bytes(&mut self, bytes: &[u8]) {
+		self.len(bytes.len());
+		self.0.extend_from_slice(bytes);
+	}
+
+	fn string(&mut self, value: &str) {
+		self.bytes(value.as_bytes());
+	}
+
+	fn samples(&mut self, samples: &[i16]) {
+		self.len(samples.len());
+		for sample in samples {
+			self.i16(*sample);
+		}
+	}
+}
+
+/// Reads a message back.
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+	fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+		if self.0.len() < len {
+			return Err(Error::RunnerProtocol("message is truncated".into()));
+		}
+
+		let (taken, rest) = self.0.split_at(len);
+		self.0 = rest;
+		Ok(taken)
+	}
+
+	fn u8(&mut self) -> Result<u8> {
+		Ok(self.take(1)?[0])
+	}
+
+	fn i16(&mut self) -> Result<i16> {
+		Ok(i16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+	}
+
+	fn u32(&mut self) -> Result<u32> {
+		Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+	}
+
+	fn u64(&mut self) -> Result<u64> {
+		Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+	}
+
+	fn f64(&mut self) -> Result<f64> {
+		Ok(f64::from_bits(self.u64()?))
+	}
+
+	fn len(&mut self) -> Result<usize> {
+		Ok(self.u32()? as usize)
+	}
+
+	fn bytes(&mut self) -> Result<Vec<u8>> {
+		let len = self.len()?;
+		Ok(self.take(len)?.to_vec())
+	}
+
+	fn string(&mut self) -> Result<String> {
+		String::from_utf8(self.bytes()?)
+			.map_err(|_| Error::RunnerProtocol("string is not valid UTF-8".into()))
+	}
+
+	fn path(&mut self) -> Result<PathBuf> {
+		use std::os::unix::ffi::OsStringExt;
+		Ok(PathBuf::from(std::ffi::OsString::from_vec(self.bytes()?)))
+	}
+
+	fn samples(&mut self) -> Result<Vec<i16>> {
+		let len = self.len()?;
+		let bytes = self.take(len.checked_mul(2).ok_or_else(Self::too_long)?)?;
+
+		Ok(bytes
+			.chunks_exact(2)
+			.map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+			.collect())
+	}
+
+	fn too_long() -> Error {
+		Error::RunnerProtocol("length is too long".into())
+	}
+
+	fn unknown_tag(tag: u8) -> Error {
+		Error::RunnerProtocol(format!("unknown message tag {tag}"))
+	}
+}
+
+/// Encodes an error the runner's frontend gave. Errors the caller might want to handle are sent
+/// as themselves; everything else is only sent as a message.
+fn encode_error(encoder: &mut Encoder, err: &Error) {
+	use std::os::unix::ffi::OsStrExt;
+
+	match err {
+		Error::CoreNotLoaded => encoder.u8(1),
+		Error::CoreAlreadyLoaded => encoder.u8(2),
+		Error::GameNotLoaded => encoder.u8(3),
+		Error::NoAvInfo => encoder.u8(4),
+		Error::RomLoadFailed => encoder.u8(5),
+		Error::NoGameUnsupported => encoder.u8(6),
+		Error::SaveStatesUnsupported => encoder.u8(7),
+		Error::StateSaveFailed => encoder.u8(8),
+		Error::StateLoadFailed => encoder.u8(9),
+		Error::InvalidLibRetroAPI { expected, got } => {
+			encoder.u8(10);
+			encoder.u32(*expected);
+			encoder.u32(*got);
+		}
+		Error::InvalidCoreVariable(message) => {
+			encoder.u8(11);
+			encoder.string(message);
+		}
+		Error::UnknownCoreVariable(key) => {
+			encoder.u8(12);
+			encoder.string(key);
+		}
+		Error::InvalidCoreVariableValue { key, value } => {
+			encoder.u8(13);
+			encoder.string(key);
+			encoder.string(value);
+		}
+		Error::InvalidPath(path) => {
+			encoder.u8(14);
+			encoder.bytes(path.as_os_str().as_bytes());
+		}
+		Error::NoContentInArchive(path) => {
+			encoder.u8(15);
+			encoder.bytes(path.as_os_str().as_bytes());
+		}
+		Error::EmptyPlaylist(path) => {
+			encoder.u8(16);
+			encoder.bytes(path.as_os_str().as_bytes());
+		}
+		Error::ArchiveError(message) => {
+			encoder.u8(17);
+			encoder.string(message);
+		}
+		Error::InvalidAudioConfig(message) => {
+			encoder.u8(18);
+			encoder.string(message);
+		}
+		err => {
+			encoder.u8(0);
+			encoder.string(&err.to_string());
+		}
+	}
+}
+
+fn decode_error(decoder: &mut Decoder) -> Result<Error> {
+	Ok(match decoder.u8()? {
+		0 => Error::RemoteError(decoder.string()?),
+		1 => Error::CoreNotLoaded,
+		2 => Error::CoreAlreadyLoaded,
+		3 => Error::GameNotLoaded,
+		4 => Error::NoAvInfo,
+		5 => Error::RomLoadFailed,
+		6 => Error::NoGameUnsupported,
+		7 => Error::SaveStatesUnsupported,
+		8 => Error::StateSaveFailed,
+		9 => Error::StateLoadFailed,
+		10 => Error::InvalidLibRetroAPI {
+			expected: decoder.u32()?,
+			got: decoder.u32()?,
+		},
+		11 => Error::InvalidCoreVariable(decoder.string()?),
+		12 => Error::UnknownCoreVariable(decoder.string()?),
+		13 => Error::InvalidCoreVariableValue {
+			key: decoder.string()?,
+			value: decoder.string()?,
+		},
+		14 => Error::InvalidPath(decoder.path()?),
+		15 => Error::NoContentInArchive(decoder.path()?),
+		16 => Error::EmptyPlaylist(decoder.path()?),
+		17 => Error::ArchiveError(decoder.string()?),
+		18 => Error::InvalidAudioConfig(decoder.string()?),
+		tag => return Err(Decoder::unknown_tag(tag)),
+	})
+}
+
+fn write_message(writer: &mut impl Write, message: &[u8]) -> Result<()> {
+	writer.write_all(&(message.len() as u32).to_le_bytes())?;
+	writer.write_all(message)?;
+	writer.flush()?;
+	Ok(())
+}
+
+fn read_message(reader: &mut impl Read) -> Result<Vec<u8>> {
+	let mut len = [0; 4];
+	reader.read_exact(&mut len)?;
+
+	let len = u32::from_le_bytes(len) as usize;
+	if len > MAX_MESSAGE_SIZE {
+		return Err(Error::RunnerProtocol(format!(
+			"message of {len} bytes is too big"
+		)));
+	}
+
+	let mut message = vec![0; len];
+	reader.read_exact(&mut message)?;
+	Ok(message)
+}
+
+/// Sends the runner the directories its frontend should use. This is the first message sent.
+pub(crate) fn write_config(writer: &mut impl Write, config: &FrontendConfig) -> Result<()> {
+	use std::os::unix::ffi::OsStrExt;
+
+	let mut encoder = Encoder::default();
+
+	for directory in [
+		&config.system_directory,
+		&config.save_directory,
+		&config.config_directory,
+		&config.states_directory,
+	] {
+		encoder.bytes(directory.as_os_str().as_bytes());
+	}
+
+	write_message(writer, &encoder.0)
+}
+
+pub(crate) fn read_config(reader: &mut impl Read) -> Result<FrontendConfig> {
+	let message = read_message(reader)?;
+	let mut decoder = Decoder(&message);
+
+	Ok(FrontendConfig {
+		system_directory: decoder.path()?,
+		save_directory: decoder.path()?,
+		config_directory: decoder.path()?,
+		states_directory: decoder.path()?,
+	})
+}
+
+impl Request {
+	pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+		use std::os::unix::ffi::OsStrExt;
+
+		let mut encoder = Encoder::default();
+
+		match self {
+			Self::LoadCore(path) => {
+				encoder.u8(0);
+				encoder.bytes(path.as_os_str().as_bytes());
+			}
+			Self::UnloadCore => encoder.u8(1),
+			Self::LoadGame(path) => {
+				encoder.u8(2);
+				encoder.bytes(path.as_os_str().as_bytes());
+			}
+			Self::LoadNoGame => encoder.u8(3),
+			Self::UnloadGame => encoder.u8(4),
+			Self::Reset => encoder.u8(5),
+			Self::RunFrame(ports) => {
+				encoder.u8(6);
+				encoder.len(ports.len());
+
+				for input in ports {
+					encoder.u32(input.port);
+					encoder.u32(input.device);
+					encoder.len(input.values.len());
+
+					for value in &input.values {
+						encoder.u32(value.device);
+						encoder.u32(value.index);
+						encoder.u32(value.id);
+						encoder.i16(value.value);
+					}
+				}
+			}
+			Self::SaveState => encoder.u8(7),
+			Self::LoadState(state) => {
+				encoder.u8(8);
+				encoder.bytes(state);
+			}
+			Self::GetAvInfo => encoder.u8(9),
+			Self::SetVariable { key, value } => {
+				encoder.u8(10);
+				encoder.string(key);
+				encoder.string(value);
+			}
+			Self::SetAudioConfig(config) => {
+				encoder.u8(11);
+
+				match config {
+					Some(config) => {
+						encoder.u8(1);
+						encoder.u32(config.output_rate);
+						encoder.u64(config.chunk_frames as u64);
+						encoder.f64(config.max_rate_delta);
+					}
+					None => encoder.u8(0),
+				}
+			}
+			Self::Shutdown => encoder.u8(12),
+			Self::GetInputDescriptors => encoder.u8(13),
+			Self::GetControllerTypes(port) => {
+				encoder.u8(14);
+				encoder.u32(*port);
+			}
+		}
+
+		write_message(writer, &encoder.0)
+	}
+
+	pub fn read_from(reader: &mut impl Read) -> Result<Self> {
+		let message = read_message(reader)?;
+		let mut decoder = Decoder(&message);
+
+		let request = match decoder.u8()? {
+			0 => Self::LoadCore(decoder.path()?),
+			1 => Self::UnloadCore,
+			2 => Self::LoadGame(decoder.path()?),
+			3 => Self::LoadNoGame,
+			4 => Self::UnloadGame,
+			5 => Self::Reset,
+			6 => {
+				let mut ports = Vec::new();
+
+				for _ in 0..decoder.len()? {
+					let port = decoder.u32()?;
+					let device = decoder.u32()?;
+
+					let mut values = Vec::new();
+					for _ in 0..decoder.len()? {
+						values.push(InputValue {
+							device: decoder.u32()?,
+							index: decoder.u32()?,
+							id: decoder.u32()?,
+							value: decoder.i16()?,
+						});
+					}
+
+					ports.push(PortInput {
+						port,
+						device,
+						values,
+					});
+				}
+
+				Self::RunFrame(ports)
+			}
+			7 => Self::SaveState,
+			8 => Self::LoadState(decoder.bytes()?),
+			9 => Self::GetAvInfo,
+			10 => Self::SetVariable {
+				key: decoder.string()?,
+				value: decoder.string()?,
+			},
+			11 => match decoder.u8()? {
+				0 => Self::SetAudioConfig(None),
+				_ => Self::SetAudioConfig(Some(AudioConfig {
+					output_rate: decoder.u32()?,
+					chunk_frames: decoder.u64()? as usize,
+					max_rate_delta: decoder.f64()?,
+				})),
+			},
+			12 => Self::Shutdown,
+			13 => Self::GetInputDescriptors,
+			14 => Self::GetControllerTypes(decoder.u32()?),
+			tag => return Err(Decoder::unknown_tag(tag)),
+		};
+
+		Ok(request)
+	}
+}
+
+impl Response {
+	pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+		let mut encoder = Encoder::default();
+
+		encoder.len(self.events.len());
+		for event in &self.events {
+			match event {
+				Event::VideoResize { width, height } => {
+					encoder.u8(0);
+					encoder.u32(*width);
+					encoder.u32(*height);
+				}
+				Event::VideoFrame { pitch, len } => {
+					encoder.u8(1);
+					encoder.u32(*pitch);
+					encoder.u32(*len);
+				}
+				Event::Audio(samples) => {
+					encoder.u8(2);
+					encoder.samples(samples);
+				}
+			}
+		}
+
+		match &self.reply {
+			Reply::Ok => encoder.u8(0),
+			Reply::Error(err) => {
+				encoder.u8(1);
+				encode_error(&mut encoder, err);
+			}
+			Reply::State(state) => {
+				encoder.u8(2);
+				encoder.bytes(state);
+			}
+			Reply::AvInfo(info) => {
+				encoder.u8(3);
+				encoder.u32(info.base_width);
+				encoder.u32(info.base_height);
+				encoder.u32(info.max_width);
+				encoder.u32(info.max_height);
+				encoder.u32(info.aspect_ratio.to_bits());
+				encoder.f64(info.fps);
+				encoder.f64(info.sample_rate);
+			}
+			Reply::CoreLoaded { name } => {
+				encoder.u8(4);
+				encoder.string(name);
+			}
+			Reply::InputDescriptors(descriptors) => {
+				encoder.u8(5);
+				encoder.len(descriptors.len());
+
+				for desc in descriptors {
+					encoder.u32(desc.port);
+					encoder.u32(desc.device);
+					encoder.u32(desc.index);
+					encoder.u32(desc.id);
+					encoder.string(&desc.description);
+				}
+			}
+			Reply::ControllerTypes(types) => {
+				encoder.u8(6);
+				encoder.len(types.len());
+
+				for controller in types {
+					encoder.string(&controller.description);
+					encoder.u32(controller.device);
+				}
+			}
+		}
+
+		write_message(writer, &encoder.0)
+	}
+
+	pub fn read_from(reader: &mut impl Read) -> Result<Self> {
+		let message = read_message(reader)?;
+		let mut decoder = Decoder(&message);
+
+		let mut events = Vec::new();
+		for _ in 0..decoder.len()? {
+			let event = match decoder.u8()? {
+				0 => Event::VideoResize {
+					width: decoder.u32()?,
+					height: decoder.u32()?,
+				},
+				1 => Event::VideoFrame {
+					pitch: decoder.u32()?,
+					len: decoder.u32()?,
+				},
+				2 => Event::Audio(decoder.samples()?),
+				tag => return Err(Decoder::unknown_tag(tag)),
+			};
+
+			events.push(event);
+		}
+
+		let reply = match decoder.u8()? {
+			0 => Reply::Ok,
+			1 => Reply::Error(decode_error(&mut decoder)?),
+			2 => Reply::State(decoder.bytes()?),
+			3 => Reply::AvInfo(AvInfo {
+				base_width: decoder.u32()?,
+				base_height: decoder.u32()?,
+				max_width: decoder.u32()?,
+				max_height: decoder.u32()?,
+				aspect_ratio: f32::from_bits(decoder.u32()?),
+				fps: decoder.f64()?,
+				sample_rate: decoder.f64()?,
+			}),
+			4 => Reply::CoreLoaded {
+				name: decoder.string()?,
+			},
+			5 => {
+				let mut descriptors = Vec::new();
+
+				for _ in 0..decoder.len()? {
+					descriptors.push(InputDescriptor {
+						port: decoder.u32()?,
+						device: decoder.u32()?,
+						index: decoder.u32()?,
+						id: decoder.u32()?,
+						description: decoder.string()?,
+					});
+				}
+
+				Reply::InputDescriptors(descriptors)
+			}
+			6 => {
+				let mut types = Vec::new();
+
+				for _ in 0..decoder.len()? {
+					types.push(ControllerType {
+						description: decoder.string()?,
+						device: decoder.u32()?,
+					});
+				}
+
+				Reply::ControllerTypes(types)
+			}
+			tag => return Err(Decoder::unknown_tag(tag)),
+		};
+
+		Ok(Self { events, reply })
+	}
+}
@@ -0,0 +1,212 @@
+//! The runner process side of a [super::RemoteFrontend].
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::os::fd::FromRawFd;
+use std::os::unix::net::UnixStream;
+use std::rc::Rc;
+
+use tracing::error;
+
+use super::protocol::{self, AvInfo, Event, PortInput, Reply, Request, Response};
+use super::shm::SharedMemory;
+use super::{RUNNER_IPC_FD, RUNNER_VIDEO_FD};
+use crate::frontend::{Frontend, FrontendInterface, HwGlInitData};
+use crate::input_devices::InputDevice;
+use crate::movie::ReplayDevice;
+use crate::result::{Error, Result};
+
+/// What the frontend has given the interface since the last request.
+struct RunnerOutput {
+	events: Vec<Event>,
+	video: SharedMemory,
+}
+
+struct RunnerInterface {
+	output: Rc<RefCell<RunnerOutput>>,
+}
+
+impl FrontendInterface for RunnerInterface {
+	fn video_update(&mut self, slice: &[u32], pitch: u32) {
+		let mut output = self.output.borrow_mut();
+
+		let Some(video) = output.video.as_mut_slice().get_mut(..slice.len()) else {
+			error!(
+				"Video frame of {} pixels doesn't fit in the video buffer",
+				slice.len()
+			);
+			return;
+		};
+		video.copy_from_slice(slice);
+
+		// Only the newest frame is in the video buffer.
+		output
+			.events
+			.retain(|event| !matches!(event, Event::VideoFrame { .. }));
+		output.events.push(Event::VideoFrame {
+			pitch,
+			len: slice.len() as u32,
+		});
+	}
+
+	fn video_update_gl(&mut self, _width: u32, _height: u32) {
+		// hw_gl_init always fails, so this can't happen.
+	}
+
+	fn video_resize(&mut self, width: u32, height: u32) {
+		self.output
+			.borrow_mut()
+			.events
+			.push(Event::VideoResize { width, height });
+	}
+
+	fn audio_sample(&mut self, slice: &[i16], _size: usize) {
+		self.output
+			.borrow_mut()
+			.events
+			.push(Event::Audio(slice.to_vec()));
+	}
+
+	fn input_poll(&mut self, _devices: &mut HashMap<u32, Box<dyn InputDevice>>) {
+		// Input was already given to the devices with the RunFrame request.
+	}
+
+	fn hw_gl_init(&mut self, _width: u32, _height: u32) -> Option<HwGlInitData> {
+		// There's no way to share a GL context with the parent, so cores using OpenGL can't be run.
+		None
+	}
+
+	fn hw_gl_framebuffer(&mut self) -> u32 {
+		0
+	}
+}
+
+/// Updates the devices plugged into the frontend to match the input the parent sent.
+fn apply_input(frontend: &mut Frontend, input: Vec<PortInput>) {
+	let unplugged: Vec<u32> = frontend
+		.input_devices
+		.keys()
+		.copied()
+		.filter(|port| !input.iter().any(|input| input.port == *port))
+		.collect();
+
+	for port in unplugged {
+		frontend.unplug_input_device(port);
+	}
+
+	for input in input {
+		let plugged = frontend
+			.get_input_device(input.port)
+			.is_some_and(|device| device.device_type() == input.device);
+
+		if !plugged {
+			frontend.plug_input_device(input.port, Box::new(ReplayDevice::new(input.device)));
+		}
+
+		let device = frontend.get_input_device(input.port).unwrap();
+		device.reset();
+
+		for value in input.values {
+			device.set_input(value.device, value.index, value.id, value.value);
+		}
+	}
+}
+
+fn handle_request(frontend: &mut Frontend, request: Request) -> Result<Reply> {
+	match request {
+		Request::LoadCore(path) => {
+			frontend.load_core(path)?;
+
+			return Ok(Reply::CoreLoaded {
+				name: frontend.get_core_name()?,
+			});
+		}
+		Request::UnloadCore => frontend.unload_core()?,
+		Request::LoadGame(path) => frontend.load_game(path)?,
+		Request::LoadNoGame => frontend.load_no_game()?,
+		Request::UnloadGame => frontend.unload_game()?,
+		Request::Reset => {
+			if !frontend.core_loaded() {
+				return Err(Error::CoreNotLoaded);
+			}
+
+			frontend.reset();
+		}
+		Request::RunFrame(input) => {
+			if !frontend.game_loaded {
+				return Err(Error::GameNotLoaded);
+			}
+
+			apply_input(frontend, input);
+			frontend.run_frame();
+		}
+		Request::SaveState => return Ok(Reply::State(frontend.save_state()?)),
+		Request::LoadState(state) => frontend.load_state(&state)?,
+		Request::GetAvInfo => {
+			let av_info = frontend.get_av_info()?;
+
+			return Ok(Reply::AvInfo(AvInfo {
+				base_width: av_info.geometry.base_width,
+				base_height: av_info.geometry.base_height,
+				max_width: av_info.geometry.max_width,
+				max_height: av_info.geometry.max_height,
+				aspect_ratio: av_info.geometry.aspect_ratio,
+				fps: av_info.timing.fps,
+				sample_rate: av_info.timing.sample_rate,
+			}));
+		}
+		Request::SetVariable { key, value } => frontend.set_variable(&key, &value)?,
+		Request::SetAudioConfig(config) => frontend.set_audio_config(config)?,
+		Request::Shutdown => {}
+		Request::GetInputDescriptors => {
+			return Ok(Reply::InputDescriptors(
+				frontend.get_input_descriptors().to_vec(),
+			));
+		}
+		Request::GetControllerTypes(port) => {
+			return Ok(Reply::ControllerTypes(
+				frontend.get_controller_types(port).to_vec(),
+			));
+		}
+	}
+
+	Ok(Reply::Ok)
+}
+
+/// Runs a frontend on behalf of a [super::RemoteFrontend] in the parent process.
+/// This should be called by the program the parent spawned, and returns once the parent
+/// asks the runner to shut down.
+pub fn run_runner() -> Result<()> {
+	// SAFETY: The parent put these here for us, and nothing else uses them.
+	let mut socket = unsafe { UnixStream::from_raw_fd(RUNNER_IPC_FD) };
+	let video = unsafe { SharedMemory::from_raw_fd(RUNNER_VIDEO_FD)? };
+
+	let config = protocol::read_config(&mut socket)?;
+
+	let output = Rc::new(RefCell::new(RunnerOutput {
+		events: Vec::new(),
+		video,
+	}));
+	let interface = RunnerInterface {
+		output: output.clone(),
+	};
+
+	let mut frontend = Frontend::new(Box::new(interface), config)?;
+
+	loop {
+		let request = Request::read_from(&mut socket)?;
+		let shutdown = matches!(request, Request::Shutdown);
+
+		let reply = handle_request(&mut frontend, request).unwrap_or_else(Reply::Error);
+
+		let response = Response {
+			events: std::mem::take(&mut output.borrow_mut().events),
+			reply,
+		};
+		response.write_to(&mut socket)?;
+
+		if shutdown {
+			return Ok(());
+		}
+	}
+}
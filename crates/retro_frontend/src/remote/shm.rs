@@ -0,0 +1,92 @@
+//! Shared memory, used to pass video frames between processes without copying them through a socket.
+
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+/// A memory mapped memfd, shared with another process by passing its file descriptor on.
+pub(crate) struct SharedMemory {
+	fd: OwnedFd,
+	ptr: *mut u32,
+	len: usize,
+}
+
+impl SharedMemory {
+	/// Creates shared memory big enough for `len` pixels. Pages which are never written
+	/// don't use any memory, so this can be generous.
+	pub fn new(len: usize) -> io::Result<Self> {
+		let fd = unsafe { libc::memfd_create(c"retro_frontend_video".as_ptr(), libc::MFD_CLOEXEC) };
+		if fd == -1 {
+			return Err(io::Error::last_os_error());
+		}
+
+		let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+		let size = len * std::mem::size_of::<u32>();
+
+		if unsafe { libc::ftruncate(fd.as_raw_fd(), size as libc::off_t) } == -1 {
+			return Err(io::Error::last_os_error());
+		}
+
+		Self::map(fd, size)
+	}
+
+	/// Maps shared memory created by another process.
+	///
+	/// # Safety
+	/// `fd` must be an open memfd which nothing else in this process owns.
+	pub unsafe fn from_raw_fd(fd: RawFd) -> io::Result<Self> {
+		let fd = OwnedFd::from_raw_fd(fd);
+
+		let mut stat: libc::stat = std::mem::zeroed();
+		if libc::fstat(fd.as_raw_fd(), &mut stat) == -1 {
+			return Err(io::Error::last_os_error());
+		}
+
+		Self::map(fd, stat.st_size as usize)
+	}
+
+	fn map(fd: OwnedFd, size: usize) -> io::Result<Self> {
+		let ptr = unsafe {
+			libc::mmap(
+				std::ptr::null_mut(),
+				size,
+				libc::PROT_READ | libc::PROT_WRITE,
+				libc::MAP_SHARED,
+				fd.as_raw_fd(),
+				0,
+			)
+		};
+
+		if ptr == libc::MAP_FAILED {
+			return Err(io::Error::last_os_error());
+		}
+
+		Ok(Self {
+			fd,
+			ptr: ptr as *mut u32,
+			len: size / std::mem::size_of::<u32>(),
+		})
+	}
+
+	pub fn as_raw_fd(&self) -> RawFd {
+		self.fd.as_raw_fd()
+	}
+
+	pub fn as_slice(&self) -> &[u32] {
+		unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+	}
+
+	pub fn as_mut_slice(&mut self) -> &mut [u32] {
+		unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len) }
+	}
+}
+
+impl Drop for SharedMemory {
+	fn drop(&mut self) {
+		unsafe {
+			libc::munmap(
+				self.ptr as *mut libc::c_void,
+				self.len * std::mem::size_of::<u32>(),
+			);
+		}
+	}
+}
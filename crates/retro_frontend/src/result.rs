@@ -8,6 +8,27 @@ pub enum Error {
 	#[error(transparent)]
 	IoError(#[from] std::io::Error),
 
+	#[error("a core gave an invalid string")]
+	InvalidString(#[from] std::str::Utf8Error),
+
+	#[error("could not parse core settings")]
+	SettingsParseError(#[from] toml::de::Error),
+
+	#[error("could not serialize core settings")]
+	SettingsSerializeError(#[from] toml::ser::Error),
+
+	#[error("invalid core variable definition \"{0}\"")]
+	InvalidCoreVariable(String),
+
+	#[error("the core has no variable named \"{0}\"")]
+	UnknownCoreVariable(String),
+
+	#[error("\"{value}\" is not a valid value for core variable \"{key}\"")]
+	InvalidCoreVariableValue { key: String, value: String },
+
+	#[error("path {0} cannot be given to a core")]
+	InvalidPath(std::path::PathBuf),
+
 	#[error("a core has not provided AV info")]
 	NoAvInfo,
 
@@ -22,6 +43,78 @@ pub enum Error {
 
 	#[error("ROM load failed")]
 	RomLoadFailed,
+
+	#[error("error while reading archive: {0}")]
+	ArchiveError(String),
+
+	#[error("archive {0} contains no content the core can load")]
+	NoContentInArchive(std::path::PathBuf),
+
+	#[error("playlist {0} has no entries")]
+	EmptyPlaylist(std::path::PathBuf),
+
+	#[error("the core does not support running without a game")]
+	NoGameUnsupported,
+
+	#[error("the core has no subsystem named {0}")]
+	UnknownSubsystem(String),
+
+	#[error("invalid subsystem content: {0}")]
+	InvalidSubsystemContent(String),
+
+	#[error("no game is currently loaded into the frontend")]
+	GameNotLoaded,
+
+	#[error("the core does not support save states")]
+	SaveStatesUnsupported,
+
+	#[error("the core failed to serialize its state")]
+	StateSaveFailed,
+
+	#[error("the core refused to load the given state")]
+	StateLoadFailed,
+
+	#[error("rewinding is not enabled")]
+	RewindNotEnabled,
+
+	#[error("a movie is already being recorded or replayed")]
+	MovieActive,
+
+	#[error("no movie is being recorded")]
+	NotRecording,
+
+	#[error("invalid movie: {0}")]
+	InvalidMovie(String),
+
+	#[error("invalid audio configuration: {0}")]
+	InvalidAudioConfig(String),
+
+	#[error("error in runner process: {0}")]
+	RemoteError(String),
+
+	#[error("invalid message from runner process: {0}")]
+	RunnerProtocol(String),
+
+	#[error("core crashed: {0}")]
+	CoreCrashed(crate::remote::CrashInfo),
+
+	#[error("invalid cheat file: {0}")]
+	InvalidCheatFile(String),
+
+	#[error("no cheat exists at index {0}")]
+	InvalidCheatIndex(usize),
+
+	#[error("the core does not support disk control")]
+	DiskControlUnsupported,
+
+	#[error("the disk tray must be ejected to change disks")]
+	DiskNotEjected,
+
+	#[error("no disk exists at index {0}")]
+	InvalidDiskIndex(u32),
+
+	#[error("the core failed to perform the disk control operation")]
+	DiskControlFailed,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
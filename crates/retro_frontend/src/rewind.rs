@@ -0,0 +1,242 @@
+//! Rewinding, built on core serialization.
+//!
+//! Snapshots of the core's state are kept in a ring buffer. Only the newest snapshot is kept in full;
+//! older snapshots are stored as deltas against the snapshot after them, which are mostly zeros
+//! (and therefore compress very well with run-length encoding) since little changes between snapshots.
+
+use std::collections::VecDeque;
+
+/// Runs of at least this many unchanged bytes end a literal run in a delta.
+const MIN_ZERO_RUN: usize = 4;
+
+/// Configuration for rewinding.
+#[derive(Clone, Debug)]
+pub struct RewindConfig {
+	/// How many frames are run between snapshots. Each rewind step goes back this many frames.
+	pub interval: u32,
+
+	/// The most memory snapshots can use. Once this is reached, the oldest snapshots are thrown away.
+	pub max_bytes: usize,
+}
+
+impl Default for RewindConfig {
+	fn default() -> Self {
+		Self {
+			interval: 1,
+			max_bytes: 64 * 1024 * 1024,
+		}
+	}
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+	loop {
+		let byte = (value & 0x7f) as u8;
+		value >>= 7;
+
+		if value == 0 {
+			out.push(byte);
+			return;
+		}
+
+		out.push(byte | 0x80);
+	}
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<usize> {
+	let mut value = 0usize;
+	let mut shift = 0;
+
+	loop {
+		let byte = *data.get(*pos)?;
+		*pos += 1;
+
+		value |= ((byte & 0x7f) as usize).checked_shl(shift)?;
+		if byte & 0x80 == 0 {
+			return Some(value);
+		}
+
+		shift += 7;
+	}
+}
+
+/// Creates a delta which turns `to` back into `from`. The delta is `from` XOR `to`, run-length encoded
+/// as (unchanged run length, changed run length, changed bytes) triples.
+fn encode_delta(from: &[u8], to: &[u8]) -> Vec<u8> {
+	let len = from.len().max(to.len());
+	let xor = |i: usize| from.get(i).copied().unwrap_or(0) ^ to.get(i).copied().unwrap_or(0);
+
+	let mut delta = Vec::new();
+	write_varint(&mut delta, from.len());
+
+	let mut i = 0;
+	while i < len {
+		let zero_start = i;
+		while i < len && xor(i) == 0 {
+			i += 1;
+		}
+
+		if i == len {
+			// Trailing unchanged bytes don't need to be stored.
+			break;
+		}
+
+		let literal_start = i;
+		while i < len {
+			let zeros = (i..len.min(i + MIN_ZERO_RUN))
+				.take_while(|&j| xor(j) == 0)
+				.count();
+			if zeros == MIN_ZERO_RUN || i + zeros == len {
+				break;
+			}
+
+			i += zeros.max(1);
+		}
+
+		write_varint(&mut delta, literal_start - zero_start);
+		write_varint(&mut delta, i - literal_start);
+		delta.extend((literal_start..i).map(xor));
+	}
+
+	delta
+}
+
+/// Applies a delta made by [encode_delta] to `to`, giving back `from`.
+fn apply_delta(to: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+	let mut pos = 0;
+	let from_len = read_varint(delta, &mut pos)?;
+
+	let mut state = to.to_vec();
+	state.resize(from_len.max(to.len()), 0);
+
+	let mut offset = 0;
+	while pos < delta.len() {
+		offset += read_varint(delta, &mut pos)?;
+		let literal_len = read_varint(delta, &mut pos)?;
+
+		let literal = delta.get(pos..pos + literal_len)?;
+		let target = state.get_mut(offset..offset + literal_len)?;
+
+		for (byte, xor) in target.iter_mut().zip(literal) {
+			*byte ^= xor;
+		}
+
+		pos += literal_len;
+		offset += literal_len;
+	}
+
+	state.truncate(from_len);
+	Some(state)
+}
+
+/// A bounded buffer of state snapshots to rewind through.
+pub(crate) struct RewindBuffer {
+	config: RewindConfig,
+
+	/// Frames run since the last snapshot.
+	frames_since_snapshot: u32,
+
+	/// The newest snapshot, in full.
+	newest: Option<Vec<u8>>,
+
+	/// Deltas which each turn a snapshot into the one before it, oldest first.
+	deltas: VecDeque<Vec<u8>>,
+
+	/// Memory used by the deltas.
+	delta_bytes: usize,
+}
+
+impl RewindBuffer {
+	pub fn new(config: RewindConfig) -> Self {
+		Self {
+			config,
+			frames_since_snapshot: 0,
+			newest: None,
+			deltas: VecDeque::new(),
+			delta_bytes: 0,
+		}
+	}
+
+	/// Called once a frame has been run. Returns true if a snapshot should be taken.
+	pub fn frame_ran(&mut self) -> bool {
+		self.frames_since_snapshot += 1;
+
+		if self.frames_since_snapshot >= self.config.interval.max(1) {
+			self.frames_since_snapshot = 0;
+			return true;
+		}
+
+		false
+	}
+
+	/// Adds a snapshot, throwing away the oldest snapshots if needed to stay within the memory limit.
+	pub fn push(&mut self, state: Vec<u8>) {
+		if let Some(newest) = self.newest.take() {
+			let delta = encode_delta(&newest, &state);
+			self.delta_bytes += delta.len();
+			self.deltas.push_back(delta);
+		}
+
+		self.newest = Some(state);
+
+		while self.bytes_used() > self.config.max_bytes {
+			match self.deltas.pop_front() {
+				Some(delta) => self.delta_bytes -= delta.len(),
+				None => break,
+			}
+		}
+	}
+
+	/// Steps back to the previous snapshot and returns it. It's kept as the newest snapshot, since
+	/// the core will be put back into that state. If no frames have been run since the newest snapshot
+	/// was taken, the core is already in that state, so it's skipped.
+	pub fn step_back(&mut self) -> Option<Vec<u8>> {
+		if self.frames_since_snapshot == 0 {
+			if self.len() < 2 {
+				return None;
+			}
+
+			self.drop_newest();
+		}
+
+		self.frames_since_snapshot = 0;
+		self.newest.clone()
+	}
+
+	/// Throws away the newest snapshot, rebuilding the one before it.
+	fn drop_newest(&mut self) {
+		let Some(state) = self.newest.take() else {
+			return;
+		};
+
+		if let Some(delta) = self.deltas.pop_back() {
+			self.delta_bytes -= delta.len();
+			self.newest = apply_delta(&state, &delta);
+
+			if self.newest.is_none() {
+				// This shouldn't ever happen, but if it does the older snapshots are useless.
+				self.clear();
+			}
+		}
+	}
+
+	/// Throws away all snapshots.
+	pub fn clear(&mut self) {
+		self.newest = None;
+		self.deltas.clear();
+		self.delta_bytes = 0;
+		self.frames_since_snapshot = 0;
+	}
+
+	/// Gets how many snapshots can be rewound through.
+	pub fn len(&self) -> usize {
+		match self.newest {
+			Some(_) => self.deltas.len() + 1,
+			None => 0,
+		}
+	}
+
+	/// Gets how much memory snapshots are using.
+	pub fn bytes_used(&self) -> usize {
+		self.newest.as_ref().map_or(0, Vec::len) + self.delta_bytes
+	}
+}
@@ -1,4 +1,5 @@
 use crate::libretro_sys_new::*;
+use std::ffi;
 
 pub fn bytes_per_pixel_from_libretro(pf: PixelFormat) -> u32 {
 	match pf {
@@ -7,13 +8,44 @@ pub fn bytes_per_pixel_from_libretro(pf: PixelFormat) -> u32 {
 	}
 }
 
+/// Converts a possibly NULL C string into an owned Rust string.
+/// Invalid UTF-8 is replaced instead of failing, since cores aren't always careful about it.
+///
+/// # Safety
+/// `ptr` must either be NULL or point to a valid NUL-terminated string.
+pub unsafe fn string_from_c(ptr: *const ffi::c_char) -> Option<String> {
+	if ptr.is_null() {
+		return None;
+	}
+
+	Some(ffi::CStr::from_ptr(ptr).to_string_lossy().into_owned())
+}
+
+/// Converts a possibly NULL `|` separated list of extensions from the core into lowercase extensions.
+///
+/// # Safety
+/// `ptr` must either be NULL or point to a valid NUL-terminated string.
+pub unsafe fn extensions_from_c(ptr: *const ffi::c_char) -> Vec<String> {
+	match string_from_c(ptr) {
+		Some(extensions) => extensions
+			.split('|')
+			.filter(|ext| !ext.is_empty())
+			.map(|ext| ext.to_lowercase())
+			.collect(),
+		None => Vec::new(),
+	}
+}
+
 /// Boilerplate code for dealing with NULL/otherwise terminated arrays,
 /// which converts them into a Rust slice.
 ///
 /// We rely on a user-provided callback currently to determine when iteration is complete.
 /// This *could* be replaced with a object-safe trait (and a constraint to allow us to use said trait) to codify
 /// the expected "end conditions" of a terminated array of a given type, but for now, the callback works.
-pub fn terminated_array<'a, T>(ptr: *const T, end_fn: impl Fn(&T) -> bool) -> &'a [T] {
+///
+/// # Safety
+/// `ptr` must point to an array which is terminated by an item `end_fn` returns true for.
+pub unsafe fn terminated_array<'a, T>(ptr: *const T, end_fn: impl Fn(&T) -> bool) -> &'a [T] {
 	// Make sure the array pointer itself isn't null. Strictly speaking, this check should be done
 	// *before* this is called by the user, but to avoid anything going haywire
 	// we additionally check here.
@@ -0,0 +1,61 @@
+//! Checks the `.cht` cheat file parser.
+
+use retro_frontend::cheats::parse_cht;
+use retro_frontend::result::Error;
+
+#[test]
+fn valid_cheat_files_are_parsed() {
+	let cheats = parse_cht(
+		r#"
+# Made by hand
+cheats = 2
+
+cheat0_desc = "Infinite Lives"
+cheat0_code = "00C-A1F-E6A+00D-A1F-E6A"
+cheat0_enable = true
+
+cheat1_code = "3E09-80B4"
+cheat1_enable = false
+cheat1_handler = "0"
+"#,
+	)
+	.unwrap();
+
+	assert_eq!(cheats.len(), 2);
+
+	assert_eq!(cheats[0].description, "Infinite Lives");
+	assert_eq!(cheats[0].code, "00C-A1F-E6A+00D-A1F-E6A");
+	assert!(cheats[0].enabled);
+
+	// Cheats without a description are named after their index.
+	assert_eq!(cheats[1].description, "Cheat 1");
+	assert_eq!(cheats[1].code, "3E09-80B4");
+	assert!(!cheats[1].enabled);
+
+	assert!(parse_cht("cheats = 0").unwrap().is_empty());
+}
+
+#[test]
+fn invalid_cheat_files_are_refused() {
+	let invalid = [
+		// No count.
+		"cheat0_code = \"3E09-80B4\"",
+		"cheats = two\ncheat0_code = \"3E09-80B4\"",
+		// More cheats than there are codes for.
+		"cheats = 2\ncheat0_code = \"3E09-80B4\"",
+		// Counts aren't trusted before the cheats are found.
+		"cheats = 99999999999",
+		// Indices have to start at 0 and be contiguous.
+		"cheats = 2\ncheat0_code = \"3E09-80B4\"\ncheat2_code = \"3E09-80B5\"",
+		"cheats = 1\ncheat1_code = \"3E09-80B4\"",
+		// Not a key/value pair.
+		"cheats = 1\ncheat0_code",
+	];
+
+	for data in invalid {
+		assert!(
+			matches!(parse_cht(data), Err(Error::InvalidCheatFile(_))),
+			"{data}"
+		);
+	}
+}
@@ -0,0 +1,406 @@
+//! Drives the test core through runner processes.
+
+use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+use std::rc::Rc;
+use std::time::Duration;
+
+use retro_frontend::audio::AudioConfig;
+use retro_frontend::frontend::{FrontendConfig, FrontendInterface, HwGlInitData};
+use retro_frontend::input_devices::{AnalogRetroPad, InputDevice, RetroPad};
+use retro_frontend::remote::{CrashReason, RemoteFrontend};
+use retro_frontend::result::Error;
+
+use retro_test_core as core;
+
+const WHITE: u32 = 0x00ffffff;
+
+#[derive(Default)]
+struct Output {
+	video: Vec<u32>,
+	resizes: Vec<(u32, u32)>,
+	audio: Vec<i16>,
+}
+
+struct TestInterface {
+	output: Rc<RefCell<Output>>,
+}
+
+impl FrontendInterface for TestInterface {
+	fn video_update(&mut self, slice: &[u32], _pitch: u32) {
+		self.output.borrow_mut().video = slice.to_vec();
+	}
+
+	fn video_update_gl(&mut self, _width: u32, _height: u32) {
+		panic!("The test core doesn't use OpenGL");
+	}
+
+	fn video_resize(&mut self, width: u32, height: u32) {
+		self.output.borrow_mut().resizes.push((width, height));
+	}
+
+	fn audio_sample(&mut self, slice: &[i16], size: usize) {
+		assert_eq!(slice.len(), size * 2);
+		self.output.borrow_mut().audio.extend_from_slice(slice);
+	}
+
+	fn input_poll(&mut self, _devices: &mut HashMap<u32, Box<dyn InputDevice>>) {}
+
+	fn hw_gl_init(&mut self, _width: u32, _height: u32) -> Option<HwGlInitData> {
+		None
+	}
+
+	fn hw_gl_framebuffer(&mut self) -> u32 {
+		0
+	}
+}
+
+/// A runner with the test core loaded.
+struct Harness {
+	frontend: RemoteFrontend,
+	output: Rc<RefCell<Output>>,
+	_directory: tempfile::TempDir,
+}
+
+impl Harness {
+	fn new() -> Self {
+		let directory = tempfile::tempdir().unwrap();
+
+		let config = FrontendConfig {
+			system_directory: directory.path().join("system"),
+			save_directory: directory.path().join("save"),
+			config_directory: directory.path().join("config"),
+			states_directory: directory.path().join("states"),
+		};
+
+		let output = Rc::new(RefCell::new(Output::default()));
+		let interface = TestInterface {
+			output: output.clone(),
+		};
+
+		let mut frontend = RemoteFrontend::spawn(
+			Command::new(env!("CARGO_BIN_EXE_retro_runner")),
+			Box::new(interface),
+			&config,
+		)
+		.unwrap();
+		frontend.load_core(core_path()).unwrap();
+		frontend.plug_input_device(0, Box::new(RetroPad::new()));
+
+		Self {
+			frontend,
+			output,
+			_directory: directory,
+		}
+	}
+
+	fn output(&self) -> Ref<'_, Output> {
+		self.output.borrow()
+	}
+
+	fn pad(&mut self) -> &mut dyn InputDevice {
+		self.frontend.get_input_device(0).unwrap()
+	}
+
+	fn last_audio_sample(&self) -> i16 {
+		*self.output().audio.last().unwrap()
+	}
+}
+
+fn core_path() -> PathBuf {
+	let deps = std::env::current_exe()
+		.unwrap()
+		.parent()
+		.unwrap()
+		.to_path_buf();
+	deps.join(format!(
+		"{}retro_test_core{}",
+		std::env::consts::DLL_PREFIX,
+		std::env::consts::DLL_SUFFIX
+	))
+}
+
+#[test]
+fn frames_are_run_remotely() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+
+	let av_info = harness.frontend.get_av_info().unwrap();
+	assert_eq!(av_info.geometry.base_width, core::WIDTH);
+	assert_eq!(av_info.timing.fps, core::FPS);
+
+	harness.frontend.run_frame().unwrap();
+	assert_eq!(harness.output().resizes, [(core::WIDTH, core::HEIGHT)]);
+	assert!(harness.output().video.iter().all(|&pixel| pixel == 0));
+	assert_eq!(harness.output().audio.len(), core::AUDIO_FRAMES * 2);
+
+	harness.pad().press_button(0, None);
+	harness.frontend.run_frame().unwrap();
+	assert_eq!(
+		harness.output().video.len(),
+		(core::WIDTH * core::HEIGHT) as usize
+	);
+	assert!(harness.output().video.iter().all(|&pixel| pixel == WHITE));
+	assert_eq!(harness.last_audio_sample(), 2);
+}
+
+#[test]
+fn analog_input_reaches_the_runner() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+
+	let mut pad = AnalogRetroPad::new();
+	pad.set_stick(libretro_sys::DEVICE_INDEX_ANALOG_RIGHT, 0x7fff, 0);
+	harness.frontend.plug_input_device(0, Box::new(pad));
+
+	harness.frontend.run_frame().unwrap();
+	assert!(harness.output().video.iter().all(|&pixel| pixel == 0));
+
+	let mut pad = AnalogRetroPad::new();
+	pad.set_stick(libretro_sys::DEVICE_INDEX_ANALOG_LEFT, 0x7fff, 0);
+	harness.frontend.plug_input_device(0, Box::new(pad));
+
+	harness.frontend.run_frame().unwrap();
+	assert!(harness.output().video.iter().all(|&pixel| pixel == WHITE));
+}
+
+#[test]
+fn input_info_is_passed_back() {
+	let mut harness = Harness::new();
+
+	let types = harness.frontend.get_controller_types(0).unwrap();
+	assert_eq!(types.len(), core::CONTROLLER_TYPES.len());
+	for (controller, (description, device)) in types.iter().zip(core::CONTROLLER_TYPES) {
+		assert_eq!(controller.description, description);
+		assert_eq!(controller.device, device);
+	}
+
+	assert!(harness.frontend.get_input_descriptors().unwrap().is_empty());
+	harness.frontend.load_no_game().unwrap();
+
+	for (id, description) in core::BUTTON_DESCRIPTIONS {
+		assert_eq!(
+			harness
+				.frontend
+				.get_input_description(0, libretro_sys::DEVICE_JOYPAD, 0, id)
+				.unwrap()
+				.as_deref(),
+			Some(description)
+		);
+	}
+
+	harness.frontend.unload_game().unwrap();
+	assert!(harness.frontend.get_input_descriptors().unwrap().is_empty());
+}
+
+#[test]
+fn errors_are_passed_back() {
+	let mut harness = Harness::new();
+
+	assert!(matches!(
+		harness.frontend.run_frame(),
+		Err(Error::GameNotLoaded)
+	));
+	assert!(matches!(
+		harness
+			.frontend
+			.set_variable(core::PIXEL_FORMAT_VARIABLE, "rgb888"),
+		Err(Error::InvalidCoreVariableValue { key, value })
+			if key == core::PIXEL_FORMAT_VARIABLE && value == "rgb888"
+	));
+
+	assert!(matches!(
+		harness.frontend.set_audio_config(Some(AudioConfig {
+			chunk_frames: 0,
+			..Default::default()
+		})),
+		Err(Error::InvalidAudioConfig(_))
+	));
+
+	// The runner keeps going afterwards.
+	harness.frontend.load_no_game().unwrap();
+	harness.frontend.run_frame().unwrap();
+}
+
+#[test]
+fn save_states_round_trip_remotely() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+
+	for _ in 0..3 {
+		harness.frontend.run_frame().unwrap();
+	}
+
+	let state = harness.frontend.save_state().unwrap();
+	assert_eq!(state.len(), core::SERIALIZE_SIZE);
+
+	harness.frontend.run_frame().unwrap();
+	harness.frontend.load_state(&state).unwrap();
+	harness.frontend.run_frame().unwrap();
+	assert_eq!(harness.last_audio_sample(), 4);
+}
+
+#[test]
+fn instances_run_side_by_side() {
+	let mut first = Harness::new();
+	let mut second = Harness::new();
+	first.frontend.load_no_game().unwrap();
+	second.frontend.load_no_game().unwrap();
+
+	first.pad().press_button(0, None);
+	for _ in 0..3 {
+		first.frontend.run_frame().unwrap();
+	}
+	second.frontend.run_frame().unwrap();
+
+	assert!(first.output().video.iter().all(|&pixel| pixel == WHITE));
+	assert!(second.output().video.iter().all(|&pixel| pixel == 0));
+	assert_eq!(first.last_audio_sample(), 3);
+	assert_eq!(second.last_audio_sample(), 1);
+}
+
+#[test]
+fn crashes_are_reported() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+
+	for _ in 0..3 {
+		harness.frontend.run_frame().unwrap();
+	}
+
+	harness.pad().press_button(core::CRASH_BUTTON, None);
+	let Err(Error::CoreCrashed(crash)) = harness.frontend.run_frame() else {
+		panic!("The core should have crashed");
+	};
+
+	assert_eq!(crash.core_name.as_deref(), Some(core::LIBRARY_NAME));
+	assert_eq!(crash.frame, 3);
+	assert_eq!(crash.reason, CrashReason::Signal(libc::SIGABRT));
+
+	// Nothing else works until the runner is restarted.
+	assert!(matches!(
+		harness.frontend.save_state(),
+		Err(Error::CoreCrashed(_))
+	));
+	assert!(harness.frontend.get_crash().is_some());
+
+	harness.pad().reset();
+	harness.frontend.restart().unwrap();
+	harness.frontend.run_frame().unwrap();
+	assert_eq!(harness.last_audio_sample(), 1);
+}
+
+#[test]
+fn crashed_runners_are_restarted_from_save_states() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+	harness.frontend.enable_auto_restart(2);
+
+	for _ in 0..5 {
+		harness.frontend.run_frame().unwrap();
+	}
+
+	let pid = harness.frontend.id();
+	harness.pad().press_button(core::CRASH_BUTTON, None);
+	assert!(matches!(
+		harness.frontend.run_frame(),
+		Err(Error::CoreCrashed(_))
+	));
+
+	assert_ne!(harness.frontend.id(), pid);
+	assert!(harness.frontend.get_crash().is_none());
+	assert_eq!(harness.frontend.get_frame(), 4);
+
+	harness.pad().reset();
+	harness.frontend.run_frame().unwrap();
+	assert_eq!(harness.last_audio_sample(), 5);
+}
+
+#[test]
+fn loaded_states_are_restarted_from() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+	harness.frontend.enable_auto_restart(1000);
+
+	for _ in 0..3 {
+		harness.frontend.run_frame().unwrap();
+	}
+	let state = harness.frontend.save_state().unwrap();
+
+	for _ in 0..3 {
+		harness.frontend.run_frame().unwrap();
+	}
+	harness.frontend.save_state().unwrap();
+
+	harness.frontend.load_state(&state).unwrap();
+	assert_eq!(harness.frontend.get_frame(), 3);
+	harness.frontend.run_frame().unwrap();
+
+	harness.pad().press_button(core::CRASH_BUTTON, None);
+	let Err(Error::CoreCrashed(crash)) = harness.frontend.run_frame() else {
+		panic!("The core should have crashed");
+	};
+	assert_eq!(crash.frame, 4);
+
+	// The state which was loaded is restarted from, not the newer one which was saved.
+	assert_eq!(harness.frontend.get_frame(), 3);
+	harness.pad().reset();
+	harness.frontend.run_frame().unwrap();
+	assert_eq!(harness.last_audio_sample(), 4);
+}
+
+#[test]
+fn hung_runners_are_killed() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+	harness
+		.frontend
+		.set_request_timeout(Some(Duration::from_millis(200)))
+		.unwrap();
+
+	harness.pad().press_button(core::HANG_BUTTON, None);
+	let Err(Error::CoreCrashed(crash)) = harness.frontend.run_frame() else {
+		panic!("The core should have hung");
+	};
+	assert_eq!(crash.reason, CrashReason::TimedOut);
+
+	harness.pad().reset();
+	harness.frontend.restart().unwrap();
+	harness.frontend.run_frame().unwrap();
+	assert_eq!(harness.last_audio_sample(), 1);
+}
+
+#[test]
+fn invalid_replies_kill_the_runner() {
+	let directory = tempfile::tempdir().unwrap();
+	let config = FrontendConfig {
+		system_directory: directory.path().join("system"),
+		save_directory: directory.path().join("save"),
+		config_directory: directory.path().join("config"),
+		states_directory: directory.path().join("states"),
+	};
+
+	// Replies to the first request with a length which is far too big.
+	let mut command = Command::new("sh");
+	command.args([
+		"-c",
+		"head -c 1 <&3 >/dev/null; printf '\\377\\377\\377\\377' >&3; sleep 5",
+	]);
+
+	let mut frontend = RemoteFrontend::spawn(
+		command,
+		Box::new(TestInterface {
+			output: Rc::new(RefCell::new(Output::default())),
+		}),
+		&config,
+	)
+	.unwrap();
+
+	let Err(Error::CoreCrashed(crash)) = frontend.load_core(core_path()) else {
+		panic!("The reply should have been refused");
+	};
+	assert_eq!(crash.reason, CrashReason::InvalidReply);
+	assert!(frontend.get_crash().is_some());
+}
@@ -0,0 +1,781 @@
+//! Drives the frontend through the test core in `crates/retro_test_core`.
+
+use std::cell::{Ref, RefCell};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Mutex, MutexGuard};
+
+use retro_frontend::audio::AudioConfig;
+use retro_frontend::frontend::{Frontend, FrontendConfig, FrontendInterface, HwGlInitData};
+use retro_frontend::input_devices::{AnalogRetroPad, InputDevice, RetroPad};
+use retro_frontend::result::Error;
+use retro_frontend::rewind::RewindConfig;
+
+use retro_test_core as core;
+
+/// Only one frontend can exist at a time, so tests have to take turns.
+static FRONTEND_LOCK: Mutex<()> = Mutex::new(());
+
+const WHITE: u32 = 0x00ffffff;
+
+/// Everything the frontend gave the interface.
+#[derive(Default)]
+struct Output {
+	video: Vec<u32>,
+	pitch: u32,
+	resizes: Vec<(u32, u32)>,
+	audio: Vec<i16>,
+	audio_chunks: Vec<usize>,
+	polls: usize,
+}
+
+struct TestInterface {
+	output: Rc<RefCell<Output>>,
+}
+
+impl FrontendInterface for TestInterface {
+	fn video_update(&mut self, slice: &[u32], pitch: u32) {
+		let mut output = self.output.borrow_mut();
+		output.video = slice.to_vec();
+		output.pitch = pitch;
+	}
+
+	fn video_update_gl(&mut self, _width: u32, _height: u32) {
+		panic!("The test core doesn't use OpenGL");
+	}
+
+	fn video_resize(&mut self, width: u32, height: u32) {
+		self.output.borrow_mut().resizes.push((width, height));
+	}
+
+	fn audio_sample(&mut self, slice: &[i16], size: usize) {
+		assert_eq!(slice.len(), size * 2);
+
+		let mut output = self.output.borrow_mut();
+		output.audio.extend_from_slice(slice);
+		output.audio_chunks.push(size);
+	}
+
+	fn input_poll(&mut self, _devices: &mut HashMap<u32, Box<dyn InputDevice>>) {
+		self.output.borrow_mut().polls += 1;
+	}
+
+	fn hw_gl_init(&mut self, _width: u32, _height: u32) -> Option<HwGlInitData> {
+		None
+	}
+
+	fn hw_gl_framebuffer(&mut self) -> u32 {
+		0
+	}
+}
+
+/// A frontend with the test core loaded.
+struct Harness {
+	frontend: Box<Frontend>,
+	output: Rc<RefCell<Output>>,
+	directory: tempfile::TempDir,
+	_guard: MutexGuard<'static, ()>,
+}
+
+impl Harness {
+	fn new() -> Self {
+		let guard = FRONTEND_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+		let directory = tempfile::tempdir().unwrap();
+
+		let config = FrontendConfig {
+			system_directory: directory.path().join("system"),
+			save_directory: directory.path().join("save"),
+			config_directory: directory.path().join("config"),
+			states_directory: directory.path().join("states"),
+		};
+
+		let output = Rc::new(RefCell::new(Output::default()));
+		let interface = TestInterface {
+			output: output.clone(),
+		};
+
+		let mut frontend = Frontend::new(Box::new(interface), config).unwrap();
+		frontend.load_core(core_path()).unwrap();
+		frontend.plug_input_device(0, Box::new(RetroPad::new()));
+
+		Self {
+			frontend,
+			output,
+			directory,
+			_guard: guard,
+		}
+	}
+
+	fn output(&self) -> Ref<'_, Output> {
+		self.output.borrow()
+	}
+
+	fn pad(&mut self) -> &mut dyn InputDevice {
+		self.frontend.get_input_device(0).unwrap()
+	}
+
+	/// Gets the size of the content the core was given, which it starts counting frames from.
+	fn loaded_size(&mut self) -> u64 {
+		state_frame(&self.frontend.save_state().unwrap())
+	}
+
+	/// Gets the sample every audio frame had in the last frame the core ran.
+	fn last_audio_sample(&self) -> i16 {
+		*self.output().audio.last().unwrap()
+	}
+}
+
+/// Writes a zip archive containing files of the given names and sizes.
+fn write_zip(path: &Path, files: &[(&str, usize)]) {
+	let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+
+	for &(name, size) in files {
+		zip.start_file(name, zip::write::SimpleFileOptions::default())
+			.unwrap();
+		zip.write_all(&vec![0x55; size]).unwrap();
+	}
+
+	zip.finish().unwrap();
+}
+
+/// The test core is built next to the test binaries, since it's a dependency of them.
+fn core_path() -> PathBuf {
+	let deps = std::env::current_exe()
+		.unwrap()
+		.parent()
+		.unwrap()
+		.to_path_buf();
+	deps.join(format!(
+		"{}retro_test_core{}",
+		std::env::consts::DLL_PREFIX,
+		std::env::consts::DLL_SUFFIX
+	))
+}
+
+#[test]
+fn load_and_unload_core() {
+	let mut harness = Harness::new();
+	assert!(harness.frontend.core_loaded());
+
+	let info = harness.frontend.get_system_info().unwrap();
+	let name = unsafe { std::ffi::CStr::from_ptr(info.library_name) };
+	assert_eq!(name.to_str().unwrap(), core::LIBRARY_NAME);
+
+	assert!(harness
+		.frontend
+		.get_variable(core::PIXEL_FORMAT_VARIABLE)
+		.is_some());
+
+	harness.frontend.unload_core().unwrap();
+	assert!(!harness.frontend.core_loaded());
+	assert!(matches!(
+		harness.frontend.load_no_game(),
+		Err(Error::CoreNotLoaded)
+	));
+}
+
+#[test]
+fn input_descriptors_and_controller_info() {
+	let mut harness = Harness::new();
+
+	let types = harness.frontend.get_controller_types(0);
+	assert_eq!(types.len(), core::CONTROLLER_TYPES.len());
+	for (controller, (description, device)) in types.iter().zip(core::CONTROLLER_TYPES) {
+		assert_eq!(controller.description, description);
+		assert_eq!(controller.device, device);
+		assert_eq!(controller.base_device(), libretro_sys::DEVICE_JOYPAD);
+	}
+	assert!(harness.frontend.get_controller_types(1).is_empty());
+
+	// Descriptors are given when the game is loaded.
+	assert!(harness.frontend.get_input_descriptors().is_empty());
+	harness.frontend.load_no_game().unwrap();
+
+	assert_eq!(
+		harness.frontend.get_input_descriptors().len(),
+		core::BUTTON_DESCRIPTIONS.len()
+	);
+	for (id, description) in core::BUTTON_DESCRIPTIONS {
+		assert_eq!(
+			harness
+				.frontend
+				.get_input_description(0, libretro_sys::DEVICE_JOYPAD, 0, id),
+			Some(description)
+		);
+	}
+
+	// Descriptors belong to the game, but controller types belong to the core.
+	harness.frontend.unload_game().unwrap();
+	assert!(harness.frontend.get_input_descriptors().is_empty());
+	assert_eq!(
+		harness.frontend.get_controller_types(0).len(),
+		core::CONTROLLER_TYPES.len()
+	);
+
+	harness.frontend.unload_core().unwrap();
+	assert!(harness.frontend.get_input_descriptors().is_empty());
+	assert!(harness.frontend.get_controller_types(0).is_empty());
+}
+
+#[test]
+fn load_game_from_file() {
+	let mut harness = Harness::new();
+
+	let game_path = harness.directory.path().join("game.bin");
+	std::fs::write(&game_path, [0u8; 64]).unwrap();
+
+	harness.frontend.load_game(&game_path).unwrap();
+	harness.frontend.run_frame();
+	assert_eq!(
+		harness.output().video.len(),
+		(core::WIDTH * core::HEIGHT) as usize
+	);
+
+	harness.frontend.unload_game().unwrap();
+}
+
+#[test]
+fn loading_a_game_replaces_the_last_one() {
+	let mut harness = Harness::new();
+
+	let first = harness.directory.path().join("first.bin");
+	let second = harness.directory.path().join("second.bin");
+	std::fs::write(&first, [0u8; 64]).unwrap();
+	std::fs::write(&second, [0u8; 32]).unwrap();
+
+	harness.frontend.load_game(&first).unwrap();
+	harness.frontend.run_frame();
+
+	harness.frontend.load_game(&second).unwrap();
+	assert_eq!(harness.loaded_size(), 32);
+	harness.frontend.run_frame();
+	assert_eq!(harness.last_audio_sample(), 33);
+
+	harness.frontend.load_no_game().unwrap();
+	assert_eq!(harness.loaded_size(), 0);
+
+	// Content which can't be read doesn't disturb the game that's running.
+	let missing = harness.directory.path().join("missing.zip#game.bin");
+	assert!(harness.frontend.load_game(&missing).is_err());
+	harness.frontend.run_frame();
+	assert_eq!(harness.last_audio_sample(), 1);
+}
+
+#[test]
+fn load_game_from_zip() {
+	let mut harness = Harness::new();
+
+	let archive = harness.directory.path().join("game.zip");
+	write_zip(
+		&archive,
+		&[("readme.txt", 16), ("game.bin", 100), ("other.bin", 50)],
+	);
+
+	// The first file the core supports is loaded, unless one is picked.
+	harness.frontend.load_game(&archive).unwrap();
+	assert_eq!(harness.loaded_size(), 100);
+	harness.frontend.unload_game().unwrap();
+
+	let other = harness.directory.path().join("game.zip#other.bin");
+	harness.frontend.load_game(&other).unwrap();
+	assert_eq!(harness.loaded_size(), 50);
+	harness.frontend.unload_game().unwrap();
+
+	let missing = harness.directory.path().join("game.zip#missing.bin");
+	assert!(matches!(
+		harness.frontend.load_game(&missing),
+		Err(Error::ArchiveError(_))
+	));
+
+	// Content the core wants a path to is extracted.
+	let archive = harness.directory.path().join("fullpath.zip");
+	let name = format!("game.{}", core::FULLPATH_EXTENSION);
+	write_zip(&archive, &[(name.as_str(), 70)]);
+	harness.frontend.load_game(&archive).unwrap();
+	assert_eq!(harness.loaded_size(), 70);
+	harness.frontend.unload_game().unwrap();
+
+	let archive = harness.directory.path().join("nothing.zip");
+	write_zip(&archive, &[("readme.txt", 16)]);
+	assert!(matches!(
+		harness.frontend.load_game(&archive),
+		Err(Error::NoContentInArchive(_))
+	));
+}
+
+#[test]
+fn load_game_from_playlist() {
+	let mut harness = Harness::new();
+
+	let disks = harness.directory.path().join("disks");
+	std::fs::create_dir(&disks).unwrap();
+	std::fs::write(disks.join("disk1.bin"), [0u8; 40]).unwrap();
+	std::fs::write(disks.join("disk2.bin"), [0u8; 80]).unwrap();
+
+	// Entries are relative to the playlist. The core has no disk control interface,
+	// so only the first disk is loaded.
+	let playlist = harness.directory.path().join("game.m3u");
+	std::fs::write(
+		&playlist,
+		"#EXTM3U\n\n  # Not a disk\ndisks/disk1.bin|Disk 1\n\ndisks/disk2.bin\n",
+	)
+	.unwrap();
+	harness.frontend.load_game(&playlist).unwrap();
+	assert_eq!(harness.loaded_size(), 40);
+	harness.frontend.unload_game().unwrap();
+
+	let playlist = disks.join("reversed.m3u");
+	std::fs::write(&playlist, "disk2.bin\r\ndisk1.bin\r\n").unwrap();
+	harness.frontend.load_game(&playlist).unwrap();
+	assert_eq!(harness.loaded_size(), 80);
+	harness.frontend.unload_game().unwrap();
+
+	let playlist = harness.directory.path().join("empty.m3u");
+	std::fs::write(&playlist, "#EXTM3U\n\n# Nothing here\n").unwrap();
+	assert!(matches!(
+		harness.frontend.load_game(&playlist),
+		Err(Error::EmptyPlaylist(_))
+	));
+}
+
+#[test]
+fn broken_zips_are_handled() {
+	let mut harness = Harness::new();
+
+	// A zip which claims its file is far bigger than any buffer could be.
+	let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+	zip.start_file(
+		"game.bin",
+		zip::write::SimpleFileOptions::default().large_file(true),
+	)
+	.unwrap();
+	zip.write_all(&[0x55; 100]).unwrap();
+	let mut data = zip.finish().unwrap().into_inner();
+
+	// Zip64 extra fields give the real size, then the compressed size.
+	let real_size = [1, 0, 16, 0, 100, 0, 0, 0, 0, 0, 0, 0];
+	let mut lies = 0;
+	for i in 0..data.len() - real_size.len() {
+		if data[i..].starts_with(&real_size) {
+			data[i + 4..i + 12].copy_from_slice(&(1u64 << 62).to_le_bytes());
+			lies += 1;
+		}
+	}
+	assert_eq!(lies, 2);
+
+	let archive = harness.directory.path().join("lying.zip");
+	std::fs::write(&archive, &data).unwrap();
+
+	// Only what's actually there is read.
+	harness.frontend.load_game(&archive).unwrap();
+	assert_eq!(harness.loaded_size(), 100);
+	harness.frontend.unload_game().unwrap();
+
+	// The end of the archive (where the list of files is) is missing.
+	let archive = harness.directory.path().join("truncated.zip");
+	std::fs::write(&archive, &data[..data.len() / 2]).unwrap();
+	assert!(matches!(
+		harness.frontend.load_game(&archive),
+		Err(Error::ArchiveError(_))
+	));
+}
+
+#[test]
+fn load_game_with_subsystem() {
+	let mut harness = Harness::new();
+
+	let subsystems = harness.frontend.get_subsystems();
+	assert_eq!(subsystems.len(), 1);
+	assert_eq!(subsystems[0].ident, core::SUBSYSTEM_IDENT);
+	assert_eq!(subsystems[0].id, core::SUBSYSTEM_ID);
+	assert_eq!(subsystems[0].roms.len(), 2);
+
+	let rom = harness.directory.path().join("game.bin");
+	let patch = harness.directory.path().join("game.patch");
+	let text = harness.directory.path().join("game.txt");
+	std::fs::write(&rom, [0u8; 64]).unwrap();
+	std::fs::write(&patch, [0u8; 32]).unwrap();
+	std::fs::write(&text, [0u8; 16]).unwrap();
+
+	let invalid: [&[&Path]; 4] = [
+		// Too much content.
+		&[&rom, &patch, &patch],
+		// The ROM is required.
+		&[],
+		// Wrong extensions.
+		&[&text],
+		&[&rom, &rom],
+	];
+	for paths in invalid {
+		assert!(matches!(
+			harness
+				.frontend
+				.load_game_special(core::SUBSYSTEM_IDENT, paths),
+			Err(Error::InvalidSubsystemContent(_))
+		));
+	}
+
+	assert!(matches!(
+		harness.frontend.load_game_special("sgb", &[&rom]),
+		Err(Error::UnknownSubsystem(_))
+	));
+
+	// The patch can be left out.
+	harness
+		.frontend
+		.load_game_special(core::SUBSYSTEM_IDENT, &[&rom])
+		.unwrap();
+	assert_eq!(harness.loaded_size(), 64);
+	harness.frontend.unload_game().unwrap();
+
+	harness
+		.frontend
+		.load_game_special(core::SUBSYSTEM_IDENT, &[&rom, &patch])
+		.unwrap();
+	assert_eq!(harness.loaded_size(), 64 + 32);
+
+	harness.frontend.run_frame();
+	assert_eq!(harness.last_audio_sample(), 64 + 32 + 1);
+	harness.frontend.unload_game().unwrap();
+
+	// Patches block extraction, so they're given to the core still zipped.
+	let zipped_patch = harness.directory.path().join("patch.zip");
+	write_zip(&zipped_patch, &[("game.patch", 32)]);
+	harness
+		.frontend
+		.load_game_special(core::SUBSYSTEM_IDENT, &[&rom, &zipped_patch])
+		.unwrap();
+	assert_eq!(
+		harness.loaded_size(),
+		64 + std::fs::metadata(&zipped_patch).unwrap().len()
+	);
+}
+
+#[test]
+fn video_xrgb8888() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+
+	harness.frontend.run_frame();
+	assert_eq!(harness.output().resizes, [(core::WIDTH, core::HEIGHT)]);
+	assert_eq!(harness.output().pitch, core::WIDTH);
+	assert!(harness.output().video.iter().all(|&pixel| pixel == 0));
+
+	harness.pad().press_button(0, None);
+	harness.frontend.run_frame();
+	assert!(harness.output().video.iter().all(|&pixel| pixel == WHITE));
+}
+
+#[test]
+fn video_rgb565_is_converted() {
+	let mut harness = Harness::new();
+	harness
+		.frontend
+		.set_variable(core::PIXEL_FORMAT_VARIABLE, "rgb565")
+		.unwrap();
+	harness.frontend.load_no_game().unwrap();
+
+	harness.pad().press_button(0, None);
+	harness.frontend.run_frame();
+	assert_eq!(harness.output().pitch, core::WIDTH);
+	assert_eq!(
+		harness.output().video.len(),
+		(core::WIDTH * core::HEIGHT) as usize
+	);
+	assert!(harness.output().video.iter().all(|&pixel| pixel == WHITE));
+
+	// Switching back while running takes effect on the next frame.
+	harness
+		.frontend
+		.set_variable(core::PIXEL_FORMAT_VARIABLE, "xrgb8888")
+		.unwrap();
+	harness.pad().reset();
+	harness.frontend.run_frame();
+	assert!(harness.output().video.iter().all(|&pixel| pixel == 0));
+}
+
+#[test]
+fn input_is_polled_every_frame() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+
+	for _ in 0..5 {
+		harness.frontend.run_frame();
+	}
+
+	assert_eq!(harness.output().polls, 5);
+}
+
+#[test]
+fn audio_is_passed_through() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+
+	harness.frontend.run_frame();
+	assert_eq!(harness.output().audio.len(), core::AUDIO_FRAMES * 2);
+	assert!(harness.output().audio.iter().all(|&sample| sample == 1));
+}
+
+#[test]
+fn audio_is_resampled_into_chunks() {
+	const CHUNK_FRAMES: usize = 256;
+
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+	harness
+		.frontend
+		.set_audio_config(Some(AudioConfig {
+			output_rate: 48000,
+			chunk_frames: CHUNK_FRAMES,
+			..Default::default()
+		}))
+		.unwrap();
+
+	// One second of audio.
+	for _ in 0..core::FPS as usize {
+		harness.frontend.run_frame();
+	}
+
+	let output = harness.output();
+	let chunks = &output.audio_chunks;
+	assert!(chunks.iter().all(|&size| size == CHUNK_FRAMES));
+
+	let produced = chunks.len() * CHUNK_FRAMES;
+	assert!(
+		(48000 - 2 * CHUNK_FRAMES..=48000).contains(&produced),
+		"produced {produced} frames"
+	);
+}
+
+#[test]
+fn zero_sample_rates_are_refused() {
+	const CHUNK_FRAMES: usize = 256;
+
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+	harness
+		.frontend
+		.set_audio_config(Some(AudioConfig {
+			chunk_frames: CHUNK_FRAMES,
+			..Default::default()
+		}))
+		.unwrap();
+
+	harness.pad().press_button(core::ZERO_RATE_BUTTON, None);
+	for _ in 0..10 {
+		harness.frontend.run_frame();
+	}
+
+	let sample_rate = harness.frontend.get_av_info().unwrap().timing.sample_rate;
+	assert_eq!(sample_rate, core::SAMPLE_RATE);
+
+	// Audio is still resampled into chunks.
+	let output = harness.output();
+	assert!(!output.audio_chunks.is_empty());
+	assert!(output.audio_chunks.iter().all(|&size| size == CHUNK_FRAMES));
+}
+
+#[test]
+fn invalid_audio_configs_are_refused() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+
+	let configs = [
+		AudioConfig {
+			output_rate: 0,
+			..Default::default()
+		},
+		AudioConfig {
+			chunk_frames: 0,
+			..Default::default()
+		},
+		AudioConfig {
+			max_rate_delta: -1.0,
+			..Default::default()
+		},
+	];
+	for config in configs {
+		assert!(matches!(
+			harness.frontend.set_audio_config(Some(config)),
+			Err(Error::InvalidAudioConfig(_))
+		));
+	}
+
+	// Audio is still passed through as it was.
+	harness.frontend.run_frame();
+	assert_eq!(harness.output().audio.len(), core::AUDIO_FRAMES * 2);
+}
+
+#[test]
+fn save_states_round_trip() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+
+	for _ in 0..3 {
+		harness.frontend.run_frame();
+	}
+
+	let state = harness.frontend.save_state().unwrap();
+	assert_eq!(state.len(), core::SERIALIZE_SIZE);
+
+	for _ in 0..2 {
+		harness.frontend.run_frame();
+	}
+	assert_eq!(harness.last_audio_sample(), 5);
+
+	harness.frontend.load_state(&state).unwrap();
+	harness.frontend.run_frame();
+	assert_eq!(harness.last_audio_sample(), 4);
+}
+
+/// Gets the test core's frame counter out of a save state.
+fn state_frame(state: &[u8]) -> u64 {
+	u64::from_le_bytes(state.try_into().unwrap())
+}
+
+#[test]
+fn rewinding_goes_back_a_frame_at_a_time() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+	harness.frontend.enable_rewind(RewindConfig::default());
+
+	for _ in 0..5 {
+		harness.frontend.run_frame();
+	}
+
+	for frame in (1..5).rev() {
+		assert!(harness.frontend.rewind_frame().unwrap());
+		assert_eq!(state_frame(&harness.frontend.save_state().unwrap()), frame);
+	}
+
+	// The first snapshot was taken after the first frame, so there's nothing before it.
+	assert!(!harness.frontend.rewind_frame().unwrap());
+	assert_eq!(state_frame(&harness.frontend.save_state().unwrap()), 1);
+
+	// Running again carries on from where rewinding stopped.
+	harness.frontend.run_frame();
+	assert_eq!(harness.last_audio_sample(), 2);
+}
+
+#[test]
+fn rewinding_is_refused_during_movies() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+	harness.frontend.enable_rewind(RewindConfig::default());
+
+	harness.frontend.start_recording().unwrap();
+	for _ in 0..3 {
+		harness.frontend.run_frame();
+	}
+
+	assert!(matches!(
+		harness.frontend.rewind_frame(),
+		Err(Error::MovieActive)
+	));
+
+	let movie = harness.frontend.stop_recording().unwrap();
+	assert_eq!(movie.frame_count(), 3);
+
+	harness.frontend.start_replay(movie).unwrap();
+	harness.frontend.run_frame();
+
+	assert!(matches!(
+		harness.frontend.rewind_frame(),
+		Err(Error::MovieActive)
+	));
+	assert_eq!(state_frame(&harness.frontend.save_state().unwrap()), 1);
+}
+
+#[test]
+fn movies_replay_input() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+	harness.frontend.start_recording().unwrap();
+
+	let mut recorded = Vec::new();
+	for frame in 0..10 {
+		if frame % 3 == 0 {
+			harness.pad().press_button(0, None);
+		} else {
+			harness.pad().reset();
+		}
+
+		harness.frontend.run_frame();
+		recorded.push(harness.output().video[0]);
+	}
+
+	let movie = harness.frontend.stop_recording().unwrap();
+	assert_eq!(movie.frame_count(), 10);
+
+	// Write it out and back in, to check the file format too.
+	let mut file = Vec::new();
+	movie.write_to(&mut file).unwrap();
+	let movie = retro_frontend::movie::Movie::read_from(&mut &file[..]).unwrap();
+
+	harness.pad().reset();
+	harness.frontend.start_replay(movie).unwrap();
+
+	let mut replayed = Vec::new();
+	for _ in 0..10 {
+		harness.frontend.run_frame();
+		replayed.push(harness.output().video[0]);
+	}
+
+	assert_eq!(recorded, replayed);
+	assert_eq!(harness.last_audio_sample(), 10);
+
+	// Once the movie is over, the pad is plugged back in.
+	harness.frontend.run_frame();
+	assert!(!harness.frontend.is_replaying());
+}
+
+#[test]
+fn analog_sticks_are_routed_by_index() {
+	let mut harness = Harness::new();
+	harness.frontend.load_no_game().unwrap();
+
+	let mut pad = AnalogRetroPad::new();
+	pad.set_stick(libretro_sys::DEVICE_INDEX_ANALOG_RIGHT, 0x7fff, 0);
+	harness.frontend.plug_input_device(0, Box::new(pad));
+	harness.frontend.start_recording().unwrap();
+
+	let mut recorded = Vec::new();
+	let positions = [
+		(libretro_sys::DEVICE_INDEX_ANALOG_RIGHT, 0x7fff),
+		(libretro_sys::DEVICE_INDEX_ANALOG_LEFT, 0x7fff),
+		(
+			libretro_sys::DEVICE_INDEX_ANALOG_LEFT,
+			core::ANALOG_THRESHOLD,
+		),
+	];
+	for (index, x) in positions {
+		let mut pad = AnalogRetroPad::new();
+		pad.set_stick(index, x, 0);
+		harness.frontend.plug_input_device(0, Box::new(pad));
+
+		harness.frontend.run_frame();
+		recorded.push(harness.output().video[0]);
+	}
+
+	assert_eq!(recorded, [0, WHITE, 0]);
+
+	// Buttons are still given to the core as a RetroPad.
+	harness.pad().press_button(0, None);
+	harness.frontend.run_frame();
+	recorded.push(harness.output().video[0]);
+	assert_eq!(recorded[3], WHITE);
+
+	let movie = harness.frontend.stop_recording().unwrap();
+	harness.pad().reset();
+	harness.frontend.start_replay(movie).unwrap();
+
+	let mut replayed = Vec::new();
+	for _ in 0..recorded.len() {
+		harness.frontend.run_frame();
+		replayed.push(harness.output().video[0]);
+	}
+
+	assert_eq!(recorded, replayed);
+}
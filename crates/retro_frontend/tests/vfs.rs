@@ -0,0 +1,136 @@
+//! Checks that the VFS sandbox keeps cores inside of it.
+
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::fs::symlink;
+use std::path::Path;
+
+use retro_frontend::libretro_vfs::{OpenMode, SandboxedVfs, VfsBackend};
+
+const READ: OpenMode = OpenMode {
+	read: true,
+	write: false,
+	update_existing: false,
+};
+
+const WRITE: OpenMode = OpenMode {
+	read: false,
+	write: true,
+	update_existing: false,
+};
+
+/// A sandbox rooted at `root`, next to a directory outside of it with a file in it.
+struct Sandbox {
+	vfs: SandboxedVfs,
+	directory: tempfile::TempDir,
+}
+
+impl Sandbox {
+	fn new() -> Self {
+		let directory = tempfile::tempdir().unwrap();
+		std::fs::create_dir(directory.path().join("root")).unwrap();
+		std::fs::create_dir(directory.path().join("outside")).unwrap();
+		std::fs::write(directory.path().join("outside/secret"), "secret").unwrap();
+
+		Self {
+			vfs: SandboxedVfs::new(&[directory.path().join("root")]).unwrap(),
+			directory,
+		}
+	}
+
+	fn root(&self) -> &Path {
+		self.directory.path()
+	}
+
+	fn assert_denied(&mut self, path: &Path, mode: OpenMode) {
+		let err = self.vfs.open(path, mode).err().expect("open should fail");
+		assert_eq!(
+			err.kind(),
+			ErrorKind::PermissionDenied,
+			"{}",
+			path.display()
+		);
+	}
+}
+
+#[test]
+fn files_inside_the_root_can_be_created() {
+	let mut sandbox = Sandbox::new();
+	let path = sandbox.root().join("root/new.sav");
+
+	let mut file = sandbox.vfs.open(&path, WRITE).unwrap();
+	file.write_all(b"save").unwrap();
+	drop(file);
+
+	// Going up and back down again is fine, as long as it stays inside.
+	let path = sandbox.root().join("root/./subdir/../new.sav");
+	let mut contents = String::new();
+	sandbox
+		.vfs
+		.open(&path, READ)
+		.unwrap()
+		.read_to_string(&mut contents)
+		.unwrap();
+	assert_eq!(contents, "save");
+
+	sandbox
+		.vfs
+		.mkdir(&sandbox.root().join("root/saves"))
+		.unwrap();
+	assert!(
+		sandbox
+			.vfs
+			.stat(&sandbox.root().join("root/saves"))
+			.unwrap()
+			.is_directory
+	);
+}
+
+#[test]
+fn parent_directories_are_refused() {
+	let mut sandbox = Sandbox::new();
+
+	let path = sandbox.root().join("root/../outside/secret");
+	sandbox.assert_denied(&path, READ);
+
+	let path = sandbox.root().join("root/../outside/new");
+	sandbox.assert_denied(&path, WRITE);
+	assert!(!sandbox.root().join("outside/new").exists());
+}
+
+#[test]
+fn absolute_paths_outside_are_refused() {
+	let mut sandbox = Sandbox::new();
+
+	let path = sandbox.root().join("outside/secret");
+	sandbox.assert_denied(&path, READ);
+	sandbox.assert_denied(Path::new("/etc/passwd"), READ);
+
+	assert!(sandbox.vfs.remove(&path).is_err());
+	assert!(path.exists());
+}
+
+#[test]
+fn symlinks_out_of_the_root_are_refused() {
+	let mut sandbox = Sandbox::new();
+	let root = sandbox.root().to_path_buf();
+
+	symlink(root.join("outside/secret"), root.join("root/file_link")).unwrap();
+	sandbox.assert_denied(&root.join("root/file_link"), READ);
+
+	symlink(root.join("outside"), root.join("root/dir_link")).unwrap();
+	sandbox.assert_denied(&root.join("root/dir_link/secret"), READ);
+	sandbox.assert_denied(&root.join("root/dir_link/new"), WRITE);
+	assert!(!root.join("outside/new").exists());
+
+	// Writing through a link to a file which doesn't exist yet would create it outside.
+	symlink(
+		root.join("outside/dangling"),
+		root.join("root/dangling_link"),
+	)
+	.unwrap();
+	assert!(sandbox
+		.vfs
+		.open(&root.join("root/dangling_link"), WRITE)
+		.is_err());
+	assert!(!root.join("outside/dangling").exists());
+}
@@ -0,0 +1,569 @@
+//! A tiny libretro core used to test the frontend without a real emulator.
+//!
+//! Every frame, the core polls input and fills the screen with white if any RetroPad button on
+//! port 0 is held or its left analog stick is pushed right past [ANALOG_THRESHOLD] (black
+//! otherwise). It then outputs [AUDIO_FRAMES] frames of audio in which every sample is the frame
+//! number. Its serialized state is the frame number, so save states can be checked as well.
+//! Holding [CRASH_BUTTON] makes the core abort, and holding [HANG_BUTTON] makes it stop responding,
+//! like a buggy core would.
+//!
+//! Loading a game starts the frame counter at the size of the content the core was given, so tests
+//! can tell what it received. Content with the [FULLPATH_EXTENSION] is read from its path by the core.
+//! The core also has a subsystem, [SUBSYSTEM_IDENT], which loads a required `.bin` ROM and an
+//! optional `.patch` file, which is never extracted from archives.
+
+// These are only meant to be called by libretro frontends, which know the rules.
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::{c_char, c_uint, c_void, CStr};
+use std::sync::Mutex;
+
+use libretro_sys::*;
+
+pub const LIBRARY_NAME: &str = "Test Core";
+pub const VALID_EXTENSIONS: &str = "bin|test";
+
+/// Content with this extension is given to the core as a path, rather than loaded into memory.
+pub const FULLPATH_EXTENSION: &str = "test";
+
+pub const WIDTH: u32 = 16;
+pub const HEIGHT: u32 = 16;
+pub const FPS: f64 = 60.0;
+pub const SAMPLE_RATE: f64 = 44100.0;
+
+/// Audio frames output every video frame. All but the last are given in one batch,
+/// and the last is given on its own.
+pub const AUDIO_FRAMES: usize = 735;
+
+/// Core variable selecting the pixel format, either `xrgb8888` or `rgb565`.
+pub const PIXEL_FORMAT_VARIABLE: &str = "test_core_pixel_format";
+
+/// How far right the left analog stick has to be pushed to count as pressed.
+pub const ANALOG_THRESHOLD: i16 = 0x4000;
+
+/// RetroPad button (L3) which makes the core abort when held.
+pub const CRASH_BUTTON: u32 = DEVICE_ID_JOYPAD_L3;
+
+/// RetroPad button (R3) which makes the core hang forever when held.
+pub const HANG_BUTTON: u32 = DEVICE_ID_JOYPAD_R3;
+
+/// RetroPad button (Select) which makes the core try to change its sample rate to 0 when held.
+pub const ZERO_RATE_BUTTON: u32 = DEVICE_ID_JOYPAD_SELECT;
+
+/// Descriptions the core gives for RetroPad buttons on port 0.
+pub const BUTTON_DESCRIPTIONS: [(u32, &str); 2] =
+	[(DEVICE_ID_JOYPAD_B, "Jump"), (DEVICE_ID_JOYPAD_A, "Fire")];
+
+/// Controller types the core supports on port 0, as (description, device).
+pub const CONTROLLER_TYPES: [(&str, u32); 2] = [
+	("RetroPad", DEVICE_JOYPAD),
+	("Test Pad", (1 << DEVICE_TYPE_SHIFT) | DEVICE_JOYPAD),
+];
+
+/// Identifier of the core's subsystem.
+pub const SUBSYSTEM_IDENT: &str = "testpair";
+
+/// Type the subsystem is loaded with.
+pub const SUBSYSTEM_ID: u32 = 0x101;
+
+/// Size of the serialized state, which is the frame number.
+pub const SERIALIZE_SIZE: usize = 8;
+
+// Newer environment calls libretro-sys doesn't know about.
+const ENVIRONMENT_SET_CONTENT_INFO_OVERRIDE: c_uint = 65;
+
+#[repr(C)]
+struct ContentInfoOverride {
+	extensions: *const c_char,
+	need_fullpath: bool,
+	persistent_data: bool,
+}
+
+struct State {
+	environment: Option<EnvironmentFn>,
+	video_refresh: Option<VideoRefreshFn>,
+	audio_sample: Option<AudioSampleFn>,
+	audio_sample_batch: Option<AudioSampleBatchFn>,
+	input_poll: Option<InputPollFn>,
+	input_state: Option<InputStateFn>,
+
+	pixel_format: PixelFormat,
+	frame: u64,
+	game_loaded: bool,
+
+	video: Vec<u8>,
+	audio: Vec<i16>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+	environment: None,
+	video_refresh: None,
+	audio_sample: None,
+	audio_sample_batch: None,
+	input_poll: None,
+	input_state: None,
+
+	pixel_format: PixelFormat::ARGB8888,
+	frame: 0,
+	game_loaded: false,
+
+	video: Vec::new(),
+	audio: Vec::new(),
+});
+
+fn state() -> std::sync::MutexGuard<'static, State> {
+	// A failed assertion in one test shouldn't take down the others.
+	STATE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+impl State {
+	unsafe fn environment(&self, cmd: c_uint, data: *mut c_void) -> bool {
+		match self.environment {
+			Some(environment) => environment(cmd, data),
+			None => false,
+		}
+	}
+
+	/// Asks the frontend for the pixel format variable, and switches to it.
+	unsafe fn update_pixel_format(&mut self) {
+		let mut variable = Variable {
+			key: c"test_core_pixel_format".as_ptr(),
+			value: std::ptr::null(),
+		};
+
+		let mut format = PixelFormat::ARGB8888;
+		if self.environment(
+			ENVIRONMENT_GET_VARIABLE,
+			&mut variable as *mut _ as *mut c_void,
+		) && !variable.value.is_null()
+			&& CStr::from_ptr(variable.value) == c"rgb565"
+		{
+			format = PixelFormat::RGB565;
+		}
+
+		let mut format_value = format as c_uint;
+		if self.environment(
+			ENVIRONMENT_SET_PIXEL_FORMAT,
+			&mut format_value as *mut _ as *mut c_void,
+		) {
+			self.pixel_format = format;
+		}
+	}
+
+	/// Sets up a freshly loaded game, which starts counting frames from `frame`. Like a real core,
+	/// this fails if a game is already loaded.
+	unsafe fn set_up_game(&mut self, frame: u64) -> bool {
+		if self.game_loaded {
+			return false;
+		}
+
+		self.game_loaded = true;
+		self.frame = frame;
+		self.update_pixel_format();
+
+		let descriptor = |id, description: &CStr| InputDescriptor {
+			port: 0,
+			device: DEVICE_JOYPAD,
+			index: 0,
+			id,
+			description: description.as_ptr(),
+		};
+		let mut descriptors = [
+			descriptor(BUTTON_DESCRIPTIONS[0].0, c"Jump"),
+			descriptor(BUTTON_DESCRIPTIONS[1].0, c"Fire"),
+			InputDescriptor {
+				port: 0,
+				device: 0,
+				index: 0,
+				id: 0,
+				description: std::ptr::null(),
+			},
+		];
+		self.environment(
+			ENVIRONMENT_SET_INPUT_DESCRIPTORS,
+			descriptors.as_mut_ptr() as *mut c_void,
+		);
+
+		true
+	}
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+	let mut state = state();
+	state.environment = Some(callback);
+
+	let mut variables = [
+		Variable {
+			key: c"test_core_pixel_format".as_ptr(),
+			value: c"Pixel format; xrgb8888|rgb565".as_ptr(),
+		},
+		Variable {
+			key: std::ptr::null(),
+			value: std::ptr::null(),
+		},
+	];
+	state.environment(
+		ENVIRONMENT_SET_VARIABLES,
+		variables.as_mut_ptr() as *mut c_void,
+	);
+
+	let mut no_game = true;
+	state.environment(
+		ENVIRONMENT_SET_SUPPORT_NO_GAME,
+		&mut no_game as *mut _ as *mut c_void,
+	);
+
+	let subsystem_roms = [
+		SubsystemRomInfo {
+			desc: c"Test ROM".as_ptr(),
+			valid_extensions: c"bin".as_ptr(),
+			need_fullpath: false,
+			block_extract: false,
+			required: true,
+			memory: std::ptr::null(),
+			num_memory: 0,
+		},
+		SubsystemRomInfo {
+			desc: c"Test Patch".as_ptr(),
+			valid_extensions: c"patch".as_ptr(),
+			need_fullpath: false,
+			block_extract: true,
+			required: false,
+			memory: std::ptr::null(),
+			num_memory: 0,
+		},
+	];
+	let mut subsystems = [
+		SubsystemInfo {
+			desc: c"Test Pair".as_ptr(),
+			ident: c"testpair".as_ptr(),
+			roms: subsystem_roms.as_ptr(),
+			num_roms: subsystem_roms.len() as c_uint,
+			id: SUBSYSTEM_ID,
+		},
+		SubsystemInfo {
+			desc: std::ptr::null(),
+			ident: std::ptr::null(),
+			roms: std::ptr::null(),
+			num_roms: 0,
+			id: 0,
+		},
+	];
+	state.environment(
+		ENVIRONMENT_SET_SUBSYSTEM_INFO,
+		subsystems.as_mut_ptr() as *mut c_void,
+	);
+
+	let mut overrides = [
+		ContentInfoOverride {
+			extensions: c"test".as_ptr(),
+			need_fullpath: true,
+			persistent_data: false,
+		},
+		ContentInfoOverride {
+			extensions: std::ptr::null(),
+			need_fullpath: false,
+			persistent_data: false,
+		},
+	];
+	state.environment(
+		ENVIRONMENT_SET_CONTENT_INFO_OVERRIDE,
+		overrides.as_mut_ptr() as *mut c_void,
+	);
+
+	let controllers = [
+		ControllerDescription {
+			desc: c"RetroPad".as_ptr(),
+			id: CONTROLLER_TYPES[0].1,
+		},
+		ControllerDescription {
+			desc: c"Test Pad".as_ptr(),
+			id: CONTROLLER_TYPES[1].1,
+		},
+	];
+	let mut controller_info = [
+		ControllerInfo {
+			types: controllers.as_ptr(),
+			num_types: controllers.len() as c_uint,
+		},
+		ControllerInfo {
+			types: std::ptr::null(),
+			num_types: 0,
+		},
+	];
+	state.environment(
+		ENVIRONMENT_SET_CONTROLLER_INFO,
+		controller_info.as_mut_ptr() as *mut c_void,
+	);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+	state().video_refresh = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(callback: AudioSampleFn) {
+	state().audio_sample = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+	state().audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+	state().input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+	state().input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {
+	let mut state = state();
+	state.frame = 0;
+	state.game_loaded = false;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+	let mut state = state();
+	state.environment = None;
+	state.video_refresh = None;
+	state.audio_sample = None;
+	state.audio_sample_batch = None;
+	state.input_poll = None;
+	state.input_state = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+	API_VERSION
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut SystemInfo) {
+	*info = SystemInfo {
+		library_name: c"Test Core".as_ptr(),
+		library_version: c"1.0".as_ptr(),
+		valid_extensions: c"bin|test".as_ptr(),
+		need_fullpath: false,
+		block_extract: false,
+	};
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut SystemAvInfo) {
+	*info = av_info();
+}
+
+fn av_info() -> SystemAvInfo {
+	SystemAvInfo {
+		geometry: GameGeometry {
+			base_width: WIDTH,
+			base_height: HEIGHT,
+			max_width: WIDTH,
+			max_height: HEIGHT,
+			aspect_ratio: 0.0,
+		},
+		timing: SystemTiming {
+			fps: FPS,
+			sample_rate: SAMPLE_RATE,
+		},
+	}
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+	state().frame = 0;
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_run() {
+	let mut state = state();
+
+	let mut updated = false;
+	state.environment(
+		ENVIRONMENT_GET_VARIABLE_UPDATE,
+		&mut updated as *mut _ as *mut c_void,
+	);
+	if updated {
+		state.update_pixel_format();
+	}
+
+	if let Some(input_poll) = state.input_poll {
+		input_poll();
+	}
+
+	let mut pressed = false;
+	if let Some(input_state) = state.input_state {
+		if input_state(0, DEVICE_JOYPAD, 0, CRASH_BUTTON) != 0 {
+			std::process::abort();
+		}
+
+		if input_state(0, DEVICE_JOYPAD, 0, HANG_BUTTON) != 0 {
+			loop {
+				std::thread::sleep(std::time::Duration::from_secs(1));
+			}
+		}
+
+		if input_state(0, DEVICE_JOYPAD, 0, ZERO_RATE_BUTTON) != 0 {
+			let mut av_info = av_info();
+			av_info.timing.sample_rate = 0.0;
+			state.environment(
+				ENVIRONMENT_SET_SYSTEM_AV_INFO,
+				&mut av_info as *mut _ as *mut c_void,
+			);
+		}
+
+		for id in 0..16 {
+			pressed |= input_state(0, DEVICE_JOYPAD, 0, id) != 0;
+		}
+
+		pressed |= input_state(
+			0,
+			DEVICE_ANALOG,
+			DEVICE_INDEX_ANALOG_LEFT,
+			DEVICE_ID_ANALOG_X,
+		) > ANALOG_THRESHOLD;
+	}
+
+	state.frame += 1;
+
+	// Video
+	let pixel: &[u8] = match (state.pixel_format, pressed) {
+		(PixelFormat::RGB565, true) => &[0xff; 2],
+		(PixelFormat::RGB565, false) => &[0x00; 2],
+		(_, true) => &0x00ffffffu32.to_ne_bytes(),
+		(_, false) => &[0x00; 4],
+	};
+	let pitch = WIDTH as usize * pixel.len();
+
+	let pixels = pixel.repeat((WIDTH * HEIGHT) as usize);
+	state.video = pixels;
+
+	if let Some(video_refresh) = state.video_refresh {
+		video_refresh(state.video.as_ptr() as *const c_void, WIDTH, HEIGHT, pitch);
+	}
+
+	// Audio
+	let sample = state.frame as i16;
+	state.audio.clear();
+	state.audio.resize((AUDIO_FRAMES - 1) * 2, sample);
+
+	if let Some(audio_sample_batch) = state.audio_sample_batch {
+		audio_sample_batch(state.audio.as_ptr(), AUDIO_FRAMES - 1);
+	}
+
+	if let Some(audio_sample) = state.audio_sample {
+		audio_sample(sample, sample);
+	}
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+	SERIALIZE_SIZE
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_serialize(data: *mut c_void, size: usize) -> bool {
+	if size < SERIALIZE_SIZE {
+		return false;
+	}
+
+	let bytes = state().frame.to_le_bytes();
+	std::ptr::copy_nonoverlapping(bytes.as_ptr(), data as *mut u8, bytes.len());
+	true
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_unserialize(data: *const c_void, size: usize) -> bool {
+	if size < SERIALIZE_SIZE {
+		return false;
+	}
+
+	let mut bytes = [0; SERIALIZE_SIZE];
+	std::ptr::copy_nonoverlapping(data as *const u8, bytes.as_mut_ptr(), bytes.len());
+	state().frame = u64::from_le_bytes(bytes);
+	true
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// Gets the size of a piece of content, reading it from its path if the frontend didn't load it.
+unsafe fn content_size(info: &GameInfo) -> Option<u64> {
+	if !info.data.is_null() {
+		return Some(info.size as u64);
+	}
+
+	if info.path.is_null() {
+		return None;
+	}
+
+	let path = CStr::from_ptr(info.path).to_str().ok()?;
+	std::fs::metadata(path).ok().map(|metadata| metadata.len())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game(game: *const GameInfo) -> bool {
+	let size = match game.as_ref() {
+		Some(game) => match content_size(game) {
+			Some(size) => size,
+			None => return false,
+		},
+		None => 0,
+	};
+
+	state().set_up_game(size)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn retro_load_game_special(
+	game_type: c_uint,
+	info: *const GameInfo,
+	num_info: usize,
+) -> bool {
+	if game_type != SUBSYSTEM_ID || info.is_null() || num_info != 2 {
+		return false;
+	}
+
+	let content = std::slice::from_raw_parts(info, num_info);
+	let Some(rom_size) = content_size(&content[0]) else {
+		return false;
+	};
+	let patch_size = content_size(&content[1]).unwrap_or(0);
+
+	state().set_up_game(rom_size + patch_size)
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+	state().game_loaded = false;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+	0
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+	std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+	0
+}
@@ -1,13 +1,21 @@
+use super::audio::*;
 use super::window::*;
 
-use std::{path::Path, time::Duration};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 use anyhow::Result;
 
 use retro_frontend::{
-	frontend::{Frontend, FrontendInterface, HwGlInitData},
+	audio::AudioConfig,
+	frontend::{Frontend, FrontendConfig, FrontendInterface, HwGlInitData},
 	input_devices::{InputDevice, RetroPad},
 	libretro_sys_new,
+	movie::Movie,
+	pacer::{CatchUpPolicy, FramePacer, Speed},
+	rewind::RewindConfig,
 };
 
 use minifb::Key;
@@ -15,6 +23,12 @@ use minifb::Key;
 use letsplay_gpu as gpu;
 use gpu::egl_helpers::DeviceContext;
 
+/// How often (in frames) frame pacing statistics are logged.
+const STATS_LOG_INTERVAL: u64 = 600;
+
+const FAST_FORWARD_MULTIPLIER: f64 = 4.0;
+const SLOW_MOTION_MULTIPLIER: f64 = 0.5;
+
 /// Called by OpenGL. We use this to dump errors.
 extern "system" fn opengl_message_callback(
 	source: gl::types::GLenum,
@@ -40,11 +54,26 @@ extern "system" fn opengl_message_callback(
 }
 
 pub struct App {
-	window: AppWindow,
+	/// Shared with the [AppInterface], which draws to it.
+	window: Rc<RefCell<AppWindow>>,
+
+	frontend: Box<Frontend>,
+
+	/// Shared with the [AppInterface], which writes audio to it.
+	audio: Rc<RefCell<Option<AudioOutput>>>,
+
+	/// If set, emulation is paced by audio output instead of sleeping for a frame.
+	pace_with_audio: bool,
 
-	frontend: Option<Box<Frontend>>,
+	/// Where the movie being recorded is saved once the main loop ends.
+	movie_path: Option<PathBuf>,
+}
+
+/// What the frontend calls into. This is owned by the frontend.
+struct AppInterface {
+	window: Rc<RefCell<AppWindow>>,
 
-	pad: RetroPad,
+	audio: Rc<RefCell<Option<AudioOutput>>>,
 
 	// EGL state
 	egl_context: Option<DeviceContext>,
@@ -57,42 +86,38 @@ pub struct App {
 }
 
 impl App {
-	pub fn new() -> Box<Self> {
-		let mut boxed = Box::new(Self {
-			window: AppWindow::new(),
-			frontend: None,
-			pad: RetroPad::new(),
+	pub fn new() -> Result<Self> {
+		let window = Rc::new(RefCell::new(AppWindow::new()));
+		let audio = Rc::new(RefCell::new(None));
+
+		let interface = AppInterface {
+			window: window.clone(),
+			audio: audio.clone(),
 
 			egl_context: None,
 			framebuffer: gpu::GlFramebuffer::new(),
 			readback_buffer: Vec::new(),
-		});
+		};
+
+		Ok(Self {
+			window,
+			frontend: Frontend::new(Box::new(interface), FrontendConfig::default())?,
 
-		// SAFETY: The only way to touch the pointer involves the frontend library calling retro_run,
-		// and the core calling one of the given callbacks. Therefore this is gnarly, but "fine",
-		// since once the main loop ends, there won't be an opporturnity for said callbacks to be called again.
-		//
-		// I'm still not really sure how to tell the borrow checker that this is alright,
-		// short of Box::leak() (which I don't want to do, since ideally I'd like actual cleanup to occur).
-		let obj = &mut *boxed as *mut dyn FrontendInterface;
-		boxed.frontend = Some(Frontend::new(obj));
+			audio,
+			pace_with_audio: false,
 
-		boxed
+			movie_path: None,
+		})
 	}
 
 	fn get_frontend(&mut self) -> &mut Frontend {
-		self.frontend.as_mut().unwrap()
+		&mut self.frontend
 	}
 
 	/// Inserts our RetroPad and initalizes the display.
 	pub fn init(&mut self) {
-		// SAFETY: This too won't ever be Use-After-Free'd because the only chance to
-		// goes away on drop as well. That's a bit flaky reasoning wise, but is true.
-		//
-		// In all honesty, I'm not sure this even needs to be a *mut so I could see if
-		// making it a immutable reference works.
-		let pad = &mut self.pad as *mut dyn InputDevice;
-		self.get_frontend().plug_input_device(0, pad);
+		self.get_frontend()
+			.plug_input_device(0, Box::new(RetroPad::new()));
 
 		self.init_display();
 	}
@@ -100,7 +125,7 @@ impl App {
 	fn init_display(&mut self) {
 		let av_info = self.get_frontend().get_av_info().expect("No AV info");
 
-		self.window.resize(
+		self.window.borrow_mut().resize(
 			av_info.geometry.base_width as u16,
 			av_info.geometry.base_height as u16,
 		);
@@ -121,6 +146,147 @@ impl App {
 		Ok(())
 	}
 
+	pub fn load_no_game(&mut self) -> Result<()> {
+		self.get_frontend().load_no_game()?;
+		Ok(())
+	}
+
+	pub fn enable_rewind(&mut self) {
+		self.get_frontend().enable_rewind(RewindConfig::default());
+	}
+
+	/// Starts recording a movie, which is saved to the given path when the app exits.
+	pub fn start_recording<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+		self.get_frontend().start_recording()?;
+		self.movie_path = Some(path.as_ref().to_path_buf());
+		Ok(())
+	}
+
+	/// Replays a movie. Once it's over, the keyboard takes control again.
+	pub fn start_replay<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+		let movie = Movie::load(path)?;
+		self.get_frontend().start_replay(movie)?;
+		Ok(())
+	}
+
+	/// Sets where audio goes, and whether it paces emulation.
+	pub fn set_audio_output(&mut self, output: AudioOutput, pace_with_audio: bool) -> Result<()> {
+		self.get_frontend().set_audio_config(Some(AudioConfig {
+			output_rate: OUTPUT_RATE,
+			..Default::default()
+		}))?;
+
+		*self.audio.borrow_mut() = Some(output);
+		self.pace_with_audio = pace_with_audio;
+		Ok(())
+	}
+
+	/// Handles keys which control emulation speed: P pauses, N steps a frame while paused,
+	/// and holding Tab or E fast-forwards or slows down emulation.
+	fn handle_speed_keys(&mut self, pacer: &mut FramePacer) {
+		if self.window.borrow().is_key_pressed(Key::P) {
+			let frontend = self.get_frontend();
+			if frontend.is_paused() {
+				frontend.resume();
+			} else {
+				frontend.pause();
+			}
+		}
+
+		if self.window.borrow().is_key_pressed(Key::N) {
+			self.get_frontend().step_frame();
+		}
+
+		let speed = if self.window.borrow().is_key_down(Key::Tab) {
+			Speed::FastForward(FAST_FORWARD_MULTIPLIER)
+		} else if self.window.borrow().is_key_down(Key::E) {
+			Speed::SlowMotion(SLOW_MOTION_MULTIPLIER)
+		} else {
+			Speed::Normal
+		};
+
+		if speed != self.get_frontend().get_speed() {
+			self.get_frontend().set_speed(speed);
+			pacer.set_speed(speed);
+		}
+	}
+
+	/// The main loop. Should probably be abstracted a bit better.
+	pub fn main_loop(&mut self) {
+		let av_info = self.get_frontend().get_av_info().expect("No AV info");
+		let mut fps = av_info.timing.fps;
+		let mut pacer = FramePacer::new(fps, CatchUpPolicy::default());
+
+		while self.window.borrow().is_open() && !self.window.borrow().is_key_down(Key::Escape) {
+			// Cores can change their frame rate while running.
+			if let Ok(av_info) = self.get_frontend().get_av_info() {
+				if av_info.timing.fps != fps {
+					fps = av_info.timing.fps;
+					pacer.set_fps(fps);
+				}
+			}
+
+			self.handle_speed_keys(&mut pacer);
+
+			// Holding R rewinds.
+			let rewinding =
+				self.window.borrow().is_key_down(Key::R) && self.get_frontend().rewind_enabled();
+
+			if rewinding {
+				if let Err(err) = self.get_frontend().rewind_frame() {
+					tracing::error!("Could not rewind: {err}");
+				}
+			} else {
+				self.get_frontend().run_frame();
+			}
+
+			// Nothing will update the window while paused, so keep it responsive ourselves.
+			if self.get_frontend().is_paused() {
+				self.window.borrow_mut().update();
+			}
+
+			// Fast-forward, slow motion and rewinding can't be paced by audio, since it's muted or too slow.
+			let normal_speed = self.get_frontend().get_speed() == Speed::Normal && !rewinding;
+
+			let paced = match self.audio.borrow_mut().as_mut() {
+				Some(audio) if self.pace_with_audio && normal_speed => audio.pace(),
+				_ => false,
+			};
+
+			if !paced {
+				pacer.wait();
+			}
+
+			let stats = pacer.stats();
+			if stats.frames != 0 && stats.frames.is_multiple_of(STATS_LOG_INTERVAL) {
+				tracing::debug!(
+					"{:.2} fps, frame time {:?} avg / {:?} max, {} late, {} skipped",
+					stats.fps,
+					stats.frame_time_avg,
+					stats.frame_time_max,
+					stats.late_frames,
+					stats.skipped_frames
+				);
+			}
+		}
+
+		if let Some(path) = self.movie_path.take() {
+			let result = self
+				.get_frontend()
+				.stop_recording()
+				.and_then(|movie| movie.save(&path));
+
+			match result {
+				Ok(()) => tracing::info!("Saved movie to {}", path.display()),
+				Err(err) => tracing::error!("Could not save movie: {err}"),
+			}
+		}
+
+		self.window.borrow_mut().close();
+	}
+}
+
+impl AppInterface {
 	/// Initalizes the headless EGL context used for OpenGL rendering.
 	fn hw_gl_egl_init(&mut self) {
 		self.egl_context = Some(DeviceContext::new(0));
@@ -133,128 +299,107 @@ impl App {
 			self.egl_context.take().unwrap().destroy()
 		}
 	}
-
-	/// The main loop. Should probably be abstracted a bit better.
-	pub fn main_loop(&mut self) {
-		while self.window.is_open() && !self.window.is_key_down(Key::Escape) {
-			let av_info = self.get_frontend().get_av_info().expect("???");
-			let step_ms = (1.0 / av_info.timing.fps) * 1000.;
-			let step_duration = Duration::from_millis(step_ms as u64);
-
-			self.get_frontend().run_frame();
-			std::thread::sleep(step_duration);
-		}
-
-		self.window.close();
-	}
 }
 
-impl FrontendInterface for App {
+impl FrontendInterface for AppInterface {
 	fn video_resize(&mut self, width: u32, height: u32) {
 		tracing::info!("Resized to {width}x{height}");
 
 		if self.egl_context.is_some() {
 			self.framebuffer.resize(width, height);
-			let raw = self.framebuffer.as_raw();
-
-			// Notify the frontend layer about the new FBO ID
-			self.get_frontend().set_gl_fbo(raw);
 
 			// Resize the readback buffer
 			self.readback_buffer.resize((width * height) as usize, 0);
 		}
 
-		self.window.resize(width as u16, height as u16);
+		self.window.borrow_mut().resize(width as u16, height as u16);
 	}
 
 	fn video_update(&mut self, slice: &[u32], pitch: u32) {
-		self.window.update_buffer(&slice, pitch, false);
+		self.window.borrow_mut().update_buffer(&slice, pitch, false);
 	}
 
-	fn video_update_gl(&mut self) {
-		let dimensions = self.get_frontend().get_size();
-
+	fn video_update_gl(&mut self, width: u32, height: u32) {
 		// Read back the framebuffer
 		{
 			self.framebuffer
-				.read_pixels(&mut self.readback_buffer[..], dimensions.0, dimensions.1)
+				.read_pixels(&mut self.readback_buffer[..], width, height)
 		}
 
 		let slice = self.readback_buffer.as_slice();
-		self.window.update_buffer(slice, dimensions.0, true);
+		self.window.borrow_mut().update_buffer(slice, width, true);
 	}
 
-	fn audio_sample(&mut self, _slice: &[i16], _size: usize) {}
+	fn audio_sample(&mut self, slice: &[i16], _size: usize) {
+		let mut audio = self.audio.borrow_mut();
 
-	fn input_poll(&mut self) {
-		self.pad.reset();
+		if let Some(output) = audio.as_mut() {
+			if let Err(err) = output.write(slice) {
+				tracing::error!("Could not write audio, disabling audio output: {err}");
+				*audio = None;
+			}
+		}
+	}
 
-		if let Some(keys) = self.window.get_keys() {
+	fn input_poll(&mut self, devices: &mut HashMap<u32, Box<dyn InputDevice>>) {
+		let Some(pad) = devices.get_mut(&0) else {
+			return;
+		};
+
+		pad.reset();
+
+		if let Some(keys) = self.window.borrow().get_keys() {
 			for key in &keys {
 				match key {
 					Key::Backslash => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_SELECT, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_SELECT, None);
 					}
 					Key::Enter => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_START, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_START, None);
 					}
 					Key::Up => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_UP, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_UP, None);
 					}
 					Key::Down => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_DOWN, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_DOWN, None);
 					}
 					Key::Left => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_LEFT, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_LEFT, None);
 					}
 					Key::Right => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_RIGHT, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_RIGHT, None);
 					}
 
 					Key::S => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_B, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_B, None);
 					}
 
 					Key::A => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_A, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_A, None);
 					}
 
 					Key::Q => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_X, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_X, None);
 					}
 
 					Key::W => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_Y, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_Y, None);
 					}
 
 					Key::LeftCtrl => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_L, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_L, None);
 					}
 
 					Key::LeftShift => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_L2, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_L2, None);
 					}
 
 					Key::LeftAlt => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_R, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_R, None);
 					}
 
 					Key::Z => {
-						self.pad
-							.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_R2, None);
+						pad.press_button(libretro_sys_new::DEVICE_ID_JOYPAD_R2, None);
 					}
 
 					_ => {}
@@ -263,7 +408,7 @@ impl FrontendInterface for App {
 		}
 	}
 
-	fn hw_gl_init(&mut self) -> Option<HwGlInitData> {
+	fn hw_gl_init(&mut self, width: u32, height: u32) -> Option<HwGlInitData> {
 		// Only create a new EGL/OpenGL context if we have to.
 		if self.egl_context.is_none() {
 			// Initalize EGL
@@ -295,16 +440,19 @@ impl FrontendInterface for App {
 		}
 
 		// Create the initial FBO for the core to render to
-		let dimensions = self.get_frontend().get_size();
-		self.framebuffer.resize(dimensions.0, dimensions.1);
+		self.framebuffer.resize(width, height);
 
 		return Some(HwGlInitData {
 			get_proc_address: gpu::egl::GetProcAddress as *mut std::ffi::c_void,
 		});
 	}
+
+	fn hw_gl_framebuffer(&mut self) -> u32 {
+		self.framebuffer.as_raw()
+	}
 }
 
-impl Drop for App {
+impl Drop for AppInterface {
 	fn drop(&mut self) {
 		// Terminate EGL and GL resources if need be
 		self.hw_gl_destroy();
@@ -0,0 +1,197 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+
+/// Sample rate audio is output at.
+pub const OUTPUT_RATE: u32 = 48000;
+
+/// How far ahead of real time audio is allowed to get when pacing with audio.
+const PACING_LATENCY: Duration = Duration::from_millis(50);
+
+/// Somewhere audio (interleaved stereo signed 16-bit samples) can go.
+pub trait AudioSink {
+	fn write(&mut self, samples: &[i16]) -> io::Result<()>;
+}
+
+/// Throws audio away. Useful for audio-driven pacing on machines without audio.
+pub struct NullSink;
+
+impl AudioSink for NullSink {
+	fn write(&mut self, _samples: &[i16]) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Writes audio to a WAV file.
+pub struct WavSink {
+	file: BufWriter<File>,
+	data_len: u32,
+}
+
+impl WavSink {
+	pub fn create(path: &str) -> io::Result<Self> {
+		let mut sink = Self {
+			file: BufWriter::new(File::create(path)?),
+			data_len: 0,
+		};
+
+		// The sizes are filled in once we know them.
+		sink.write_header()?;
+		Ok(sink)
+	}
+
+	fn write_header(&mut self) -> io::Result<()> {
+		const CHANNELS: u16 = 2;
+		const BITS_PER_SAMPLE: u16 = 16;
+		let block_align = CHANNELS * BITS_PER_SAMPLE / 8;
+
+		self.file.write_all(b"RIFF")?;
+		self.file.write_all(&(36 + self.data_len).to_le_bytes())?;
+		self.file.write_all(b"WAVE")?;
+
+		self.file.write_all(b"fmt ")?;
+		self.file.write_all(&16u32.to_le_bytes())?;
+		self.file.write_all(&1u16.to_le_bytes())?; // PCM
+		self.file.write_all(&CHANNELS.to_le_bytes())?;
+		self.file.write_all(&OUTPUT_RATE.to_le_bytes())?;
+		self.file
+			.write_all(&(OUTPUT_RATE * block_align as u32).to_le_bytes())?;
+		self.file.write_all(&block_align.to_le_bytes())?;
+		self.file.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+		self.file.write_all(b"data")?;
+		self.file.write_all(&self.data_len.to_le_bytes())?;
+		Ok(())
+	}
+
+	fn finish(&mut self) -> io::Result<()> {
+		self.file.seek(SeekFrom::Start(0))?;
+		self.write_header()?;
+		self.file.flush()
+	}
+}
+
+impl AudioSink for WavSink {
+	fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+		for sample in samples {
+			self.file.write_all(&sample.to_le_bytes())?;
+		}
+
+		self.data_len = self
+			.data_len
+			.saturating_add(std::mem::size_of_val(samples) as u32);
+		Ok(())
+	}
+}
+
+impl Drop for WavSink {
+	fn drop(&mut self) {
+		if let Err(err) = self.finish() {
+			tracing::error!("Could not finish WAV file: {err}");
+		}
+	}
+}
+
+/// Pipes raw audio into the standard input of a command (e.g. `aplay` or `pacat`) to play it.
+pub struct CommandSink {
+	child: Child,
+	stdin: Option<ChildStdin>,
+}
+
+impl CommandSink {
+	pub fn spawn(command_line: &str) -> Result<Self> {
+		let mut args = command_line.split_whitespace();
+		let program = args.next().context("Audio command is empty")?;
+
+		let mut child = Command::new(program)
+			.args(args)
+			.stdin(Stdio::piped())
+			.spawn()
+			.with_context(|| format!("Could not run audio command {program}"))?;
+
+		let stdin = child.stdin.take();
+		Ok(Self { child, stdin })
+	}
+}
+
+impl AudioSink for CommandSink {
+	fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+		let stdin = self
+			.stdin
+			.as_mut()
+			.ok_or_else(|| io::Error::from(io::ErrorKind::BrokenPipe))?;
+
+		let bytes: Vec<u8> = samples
+			.iter()
+			.flat_map(|sample| sample.to_le_bytes())
+			.collect();
+		stdin.write_all(&bytes)
+	}
+}
+
+impl Drop for CommandSink {
+	fn drop(&mut self) {
+		// Closing stdin lets the command finish playing and exit.
+		self.stdin = None;
+		let _ = self.child.wait();
+	}
+}
+
+/// Audio output, which can also be used to pace emulation.
+pub struct AudioOutput {
+	sink: Box<dyn AudioSink>,
+
+	/// When the first audio was written, and how many frames have been written since.
+	start: Option<Instant>,
+	frames_written: u64,
+}
+
+impl AudioOutput {
+	/// Creates audio output from a sink specification: `null`, `wav:<path>`, or `cmd:<command line>`.
+	/// Commands are given raw signed 16-bit little-endian stereo audio at [OUTPUT_RATE].
+	pub fn from_spec(spec: &str) -> Result<Self> {
+		let sink: Box<dyn AudioSink> = match spec.split_once(':') {
+			None if spec == "null" => Box::new(NullSink),
+			Some(("wav", path)) => Box::new(
+				WavSink::create(path)
+					.with_context(|| format!("Could not create WAV file {path}"))?,
+			),
+			Some(("cmd", command_line)) => Box::new(CommandSink::spawn(command_line)?),
+			_ => {
+				bail!("Unknown audio sink \"{spec}\" (expected null, wav:<path> or cmd:<command>)")
+			}
+		};
+
+		Ok(Self {
+			sink,
+			start: None,
+			frames_written: 0,
+		})
+	}
+
+	pub fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+		self.start.get_or_insert_with(Instant::now);
+		self.frames_written += samples.len() as u64 / 2;
+		self.sink.write(samples)
+	}
+
+	/// Sleeps until the audio written so far is no more than a little ahead of real time.
+	/// Returns false if there's no audio to pace with yet.
+	pub fn pace(&mut self) -> bool {
+		let Some(start) = self.start else {
+			return false;
+		};
+
+		let audio_time = Duration::from_secs_f64(self.frames_written as f64 / OUTPUT_RATE as f64);
+		let ahead = audio_time.saturating_sub(start.elapsed());
+
+		if ahead > PACING_LATENCY {
+			std::thread::sleep(ahead - PACING_LATENCY);
+		}
+
+		true
+	}
+}
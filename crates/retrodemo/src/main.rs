@@ -4,11 +4,13 @@ use tracing_subscriber::FmtSubscriber;
 use clap::{arg, command};
 
 mod app;
+mod audio;
 mod window;
 
 use anyhow::Result;
 
 use app::*;
+use audio::AudioOutput;
 
 fn main() -> Result<()> {
 	// Setup a tracing subscriber
@@ -23,20 +25,52 @@ fn main() -> Result<()> {
 		// Not that it matters, but this is only really required for cores that require
 		// content to be loaded; that's most cores, but libretro does support the difference.
 		.arg(arg!(--rom <VALUE>).required(false))
+		.arg(
+			arg!(--audio <SINK> "Audio output: null, wav:<path>, or cmd:<command> (given raw s16le stereo 48kHz audio)")
+				.required(false),
+		)
+		.arg(arg!(--"pace-with-audio" "Pace emulation with audio output instead of sleeping"))
+		.arg(arg!(--rewind "Enable rewinding (hold R to rewind)"))
+		.arg(arg!(--record <FILE> "Record input to a movie file").required(false))
+		.arg(
+			arg!(--replay <FILE> "Replay a movie file")
+				.required(false)
+				.conflicts_with("record"),
+		)
 		.get_matches();
 
 	let core_path: &String = matches.get_one("core").unwrap();
 
-	let mut app = App::new();
+	let mut app = App::new()?;
 
 	app.load_core(core_path)?;
 
-	if let Some(rom_path) = matches.get_one::<String>("rom") {
-		app.load_game(rom_path)?;
+	if let Some(audio_spec) = matches.get_one::<String>("audio") {
+		app.set_audio_output(
+			AudioOutput::from_spec(audio_spec)?,
+			matches.get_flag("pace-with-audio"),
+		)?;
+	} else if matches.get_flag("pace-with-audio") {
+		anyhow::bail!("--pace-with-audio requires --audio");
+	}
+
+	match matches.get_one::<String>("rom") {
+		Some(rom_path) => app.load_game(rom_path)?,
+		None => app.load_no_game()?,
+	}
+
+	if matches.get_flag("rewind") {
+		app.enable_rewind();
 	}
 
 	app.init();
 
+	if let Some(movie_path) = matches.get_one::<String>("record") {
+		app.start_recording(movie_path)?;
+	} else if let Some(movie_path) = matches.get_one::<String>("replay") {
+		app.start_replay(movie_path)?;
+	}
+
 	app.main_loop();
 
 	Ok(())
@@ -1,4 +1,4 @@
-use minifb::{Key, Window, WindowOptions};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
 
 /// A wrapper over minifb
 pub struct AppWindow {
@@ -130,6 +130,21 @@ impl AppWindow {
 		}
 	}
 
+	/// Returns true if the key was pressed since the last update.
+	pub fn is_key_pressed(&self, key: Key) -> bool {
+		match self.window.as_ref() {
+			Some(window) => window.is_key_pressed(key, KeyRepeat::No),
+			None => false,
+		}
+	}
+
+	/// Processes window events without updating the framebuffer.
+	pub fn update(&mut self) {
+		if let Some(window) = self.window.as_mut() {
+			window.update();
+		}
+	}
+
 	pub fn close(&mut self) {
 		self.window = None;
 	}